@@ -7,18 +7,40 @@ use std::{
 use vec1::{vec1, Vec1};
 
 use crate::{
-    backend::{module::Call, ComputerState, Instr, RegisterContents, Scope},
+    backend::{
+        module::Call, Array, ComputerState, Function, Instr, Loop, RamPage, RegisterContents, Scope,
+    },
     err,
     error::Error,
-    frontend::{EqualityOperator, Expression, ExpressionType, Ident, Operator, Range},
+    frontend::{
+        EqualityOperator, Expression, ExpressionType, Ident, LogicalOperator, Operator, Range,
+    },
+    warning::{Type as WarningType, Warning},
 };
 
 use super::{
-    module::{call, exist, init},
-    ErrorType, Instruction, InstructionVariant,
+    module::{call, constant, exist, init},
+    ErrorType, Instruction, InstructionVariant, Options, Pass,
 };
 
 const VAR_SLOTS: usize = 32;
+/// instructions per ROM page, jumps across pages need a disc jump
+const PAGE_SIZE: usize = 64;
+/// jump addresses are a `u8`, so only this many pages are reachable
+const PAGES: usize = (u8::MAX as usize + 1) / PAGE_SIZE;
+const MAX_INSTRUCTIONS: usize = PAGES * PAGE_SIZE;
+/// jump marks are referenced by a `u8` until they are resolved
+const MAX_JUMP_MARKS: usize = u8::MAX as usize + 1;
+/// RAM is addressed in pages of 16 words and the page number is a `u8`
+const RAM_SIZE: i16 = 16 * (u8::MAX as i16 + 1);
+/// out slot `trace` writes its tag to, `io.write` can't use it in a program that traces
+const TRACE_SLOT: u8 = 4;
+/// `repeat` loops up to this count get unrolled
+const REPEAT_UNROLL_LIMIT: i16 = 4;
+/// an inline index lets the body fold, so these get unrolled further
+const INDEXED_REPEAT_UNROLL_LIMIT: i16 = 16;
+/// constant exponents up to this get unrolled into `MUL`s
+const POW_UNROLL_LIMIT: i16 = 8;
 
 type Res<T = (), E = Error> = Result<T, E>;
 
@@ -65,8 +87,79 @@ macro_rules! instr {
 /// );
 /// ```
 pub fn compile_program(ast: Vec<Expression>) -> Res<Vec<Instruction>, Vec<Error>> {
-    let compiler = Compiler::new();
-    compiler.generate_assembly(ast)
+    Ok(compile_with_options(ast, Options::default())?.0)
+}
+
+/// Like [`compile_program`], but also returns the warnings
+///
+/// # Errors
+///
+/// on any compiler error
+pub fn compile_with_warnings(
+    ast: Vec<Expression>,
+) -> Res<(Vec<Instruction>, Vec<Warning>), Vec<Error>> {
+    compile_with_options(ast, Options::default())
+}
+
+/// Like [`compile_with_warnings`], but with custom [`Options`]
+///
+/// # Errors
+///
+/// on any compiler error
+pub fn compile_with_options(
+    ast: Vec<Expression>,
+    options: Options,
+) -> Res<(Vec<Instruction>, Vec<Warning>), Vec<Error>> {
+    let compilation = compile_with_details(ast, options)?;
+    Ok((compilation.instructions, compilation.warnings))
+}
+
+/// Like [`compile_with_options`], but also returns the symbols and what each
+/// statement cost
+///
+/// # Errors
+///
+/// on any compiler error
+pub fn compile_with_details(
+    ast: Vec<Expression>,
+    options: Options,
+) -> Res<Compilation, Vec<Error>> {
+    let werror = options.werror.clone();
+    let mut compiler = Compiler::new();
+    compiler.options = options;
+    let mut compilation = compiler.generate_assembly(ast)?;
+    let (promoted, warnings): (Vec<_>, _) = compilation
+        .warnings
+        .into_iter()
+        .partition(|warning| werror.contains(&warning.typ.category()));
+    if !promoted.is_empty() {
+        return Err(promoted.into_iter().map(Error::from).collect());
+    }
+    compilation.warnings = warnings;
+    Ok(compilation)
+}
+
+#[derive(Debug)]
+pub struct Compilation {
+    pub instructions: Vec<Instruction>,
+    pub warnings: Vec<Warning>,
+    /// the names in the top-level scope and what they were assigned
+    pub symbols: Symbols,
+    /// how many of the instructions each top-level statement accounts for,
+    /// code inlined from a function counts for its definition
+    pub statement_costs: Vec<(Range, usize)>,
+}
+
+#[derive(Debug, Default)]
+pub struct Symbols {
+    /// name and slot, ordered by slot
+    pub variables: Vec<(String, u8)>,
+    /// name and value, ordered by name
+    pub inline_variables: Vec<(String, i16)>,
+    /// name, address of the first element and size, ordered by address
+    pub arrays: Vec<(String, i16, i16)>,
+    /// slots reserved by modules, like the pointer of `list`
+    pub module_slots: Vec<(&'static str, u8)>,
 }
 
 #[derive(Debug)]
@@ -74,8 +167,32 @@ pub struct Compiler {
     scopes: Vec1<Scope>,
     main_scope: Vec<Instr>,
     modules: HashSet<String>,
-    jump_marks: HashMap<u8, u8>,
+    /// alias and the module it stands for, from `use module as alias`
+    aliases: HashMap<String, String>,
+    jump_marks: HashMap<u8, usize>,
+    /// marks handed out so far, can be more than fit into `jump_marks`
+    jump_mark_count: usize,
+    /// where the first mark without an id of its own was needed
+    jump_mark_overflow: Option<usize>,
+    /// the location of every top-level statement
+    statements: Vec<Range>,
+    /// whether the program uses `trace`, which reserves [`TRACE_SLOT`]
+    traced: bool,
+    /// where `io.write` wrote to [`TRACE_SLOT`]
+    trace_slot_writes: Vec<Range>,
+    warnings: Vec<Warning>,
+    options: Options,
+    loops: Vec<Loop>,
+    functions: HashMap<String, Function>,
+    /// functions currently being inlined
+    calls: Vec<String>,
+    /// the scope of the innermost function call, the ones between it and
+    /// the global scope belong to the caller and are hidden from it
+    call_scope: usize,
     pub variables: [bool; VAR_SLOTS],
+    /// arrays are placed in RAM one before another, downwards from its end,
+    /// so they don't meet the `list` module growing up from 0
+    array_start: i16,
     pub module_state: HashMap<&'static str, Box<dyn Any>>,
 }
 
@@ -84,14 +201,27 @@ impl Compiler {
         Self {
             scopes: vec1!(Scope::default()),
             modules: HashSet::new(),
+            aliases: HashMap::new(),
             main_scope: vec![],
             jump_marks: HashMap::new(),
+            jump_mark_count: 0,
+            jump_mark_overflow: None,
+            statements: vec![],
+            traced: false,
+            trace_slot_writes: vec![],
+            warnings: vec![],
+            options: Options::default(),
+            loops: vec![],
+            functions: HashMap::new(),
+            calls: vec![],
+            call_scope: 0,
             variables: [false; VAR_SLOTS],
+            array_start: RAM_SIZE,
             module_state: HashMap::new(),
         }
     }
 
-    fn scope_len(scope: &Vec<Instr>) -> u8 {
+    fn scope_len(scope: &Vec<Instr>) -> usize {
         let mut sum = 0;
         for i in scope {
             sum += match i {
@@ -102,6 +232,18 @@ impl Compiler {
         sum
     }
 
+    /// address the next instruction will end up at
+    fn current_address(&self) -> usize {
+        self.instruction_count()
+    }
+
+    fn instruction_count(&self) -> usize {
+        self.scopes
+            .iter()
+            .map(|scope| Self::scope_len(&scope.instructions))
+            .sum()
+    }
+
     pub fn get_module_state<'a, V: 'static>(&'a mut self, key: &'static str) -> Option<&'a mut V> {
         let value = self.module_state.get_mut(key)?;
 
@@ -113,8 +255,19 @@ impl Compiler {
         last_scope.inline_variables.insert(symbol, value);
     }
 
+    /// the scopes names can be looked up in, innermost first
+    fn visible_scopes(&self) -> impl Iterator<Item = &Scope> {
+        let hidden = 1..self.call_scope;
+        self.scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(move |(index, _)| !hidden.contains(index))
+            .map(|(_, scope)| scope)
+    }
+
     fn get_inline_var(&self, symbol: &String, location: Range) -> Res<i16> {
-        for scope in self.scopes.iter().rev() {
+        for scope in self.visible_scopes() {
             let entry = scope.inline_variables.get(symbol);
             if let Some(v) = entry {
                 return Ok(*v);
@@ -126,14 +279,99 @@ impl Compiler {
         })
     }
 
+    /// reserves the RAM for the array, it isn't freed at the end of the scope
+    fn declare_array(&mut self, ident: Ident, size: &Expression) -> Res {
+        let Some(size_value) = self.try_get_constant(size) else {
+            return err!(
+                ErrorType::CompileTimeArg("The array size".to_string()),
+                size.location
+            );
+        };
+        if size_value <= 0 {
+            return err!(ErrorType::InvalidArraySize(size_value), size.location);
+        }
+        let base = self.array_start - size_value;
+        if base < 0 {
+            return err!(ErrorType::OutOfRam(size_value), size.location);
+        }
+        self.array_start = base;
+        self.last_scope_mut().arrays.insert(
+            ident.symbol,
+            Array {
+                base,
+                size: size_value,
+            },
+        );
+        Ok(())
+    }
+
+    fn get_array(&self, ident: &Ident) -> Res<Array> {
+        for scope in self.visible_scopes() {
+            if let Some(array) = scope.arrays.get(&ident.symbol) {
+                return Ok(*array);
+            }
+        }
+        err!(
+            ErrorType::NonexistentArray(ident.symbol.clone()),
+            ident.location
+        )
+    }
+
+    /// the RAM address of an element, a known index is checked against the size
+    fn array_address(&mut self, array: &Ident, index: &Expression) -> Res<Expression> {
+        let Array { base, size } = self.get_array(array)?;
+        if let Some(value) = self.try_get_constant(index) {
+            if !(0..size).contains(&value) {
+                return err!(ErrorType::IndexOutOfBounds(value, size), index.location);
+            }
+            return Ok(Expression {
+                typ: ExpressionType::NumericLiteral(base + value),
+                location: index.location,
+            });
+        }
+        Ok(Expression {
+            typ: ExpressionType::BinaryExpr {
+                left: Box::new(Expression {
+                    typ: ExpressionType::NumericLiteral(base),
+                    location: array.location,
+                }),
+                right: Box::new(index.clone()),
+                operator: Operator::Plus,
+            },
+            location: index.location,
+        })
+    }
+
+    /// array accesses go through the `ram` module, it doesn't have to be loaded
+    fn call_ram(&mut self, method: &str, args: &[Expression], location: Range) -> Res {
+        call(
+            "ram",
+            self,
+            &Call {
+                method_name: &method.to_string(),
+                args,
+                location,
+            },
+        )
+    }
+
+    /// variables are allocated from the lowest free slot
     fn get_next_available_slot(&mut self) -> Option<u8> {
         let index = self.variables.iter().position(|slot| !*slot)?;
         self.variables[index] = true;
         Some(index.try_into().unwrap_or(0))
     }
 
+    /// temps are allocated from the highest free slot, so they don't evict
+    /// the register contents of the variables below them
+    fn get_last_available_slot(&mut self) -> Option<u8> {
+        let index = self.variables.iter().rposition(|slot| !*slot)?;
+        self.variables[index] = true;
+        Some(index.try_into().unwrap_or(0))
+    }
+
     fn insert_var(&mut self, symbol: &str, location: Range) -> Res<u8> {
-        for scope in self.scopes.iter().rev() {
+        for scope in self.visible_scopes() {
             let entry = scope.variables.get(symbol);
             if let Some(v) = entry {
                 return Ok(*v);
@@ -143,12 +381,88 @@ impl Compiler {
             typ: Box::new(ErrorType::TooManyVars),
             location,
         })?;
-        self.last_scope_mut()
-            .variables
-            .insert(symbol.to_owned(), slot);
+        let scope = self.last_scope_mut();
+        scope.variables.insert(symbol.to_owned(), slot);
+        scope.unread.push(Ident {
+            symbol: symbol.to_owned(),
+            location,
+        });
         Ok(slot)
     }
 
+    fn mark_read(&mut self, symbol: &str) {
+        let hidden = 1..self.call_scope;
+        if let Some((_, scope)) = self
+            .scopes
+            .iter_mut()
+            .enumerate()
+            .rev()
+            .find(|(index, scope)| !hidden.contains(index) && scope.variables.contains_key(symbol))
+        {
+            scope.unread.retain(|ident| ident.symbol != symbol);
+        }
+    }
+
+    fn warn_unread(&mut self, unread: Vec<Ident>) {
+        for ident in unread {
+            self.warn(Warning {
+                typ: WarningType::UnusedVariable(ident.symbol),
+                location: ident.location,
+            });
+        }
+    }
+
+    /// warns about the statements after one that never finishes
+    fn warn_unreachable(&mut self, body: &[Expression]) {
+        let Some(end) = body.iter().position(never_finishes) else {
+            return;
+        };
+        if let (Some(first), Some(last)) = (body.get(end + 1), body.last()) {
+            self.warn(Warning {
+                typ: WarningType::UnreachableCode,
+                location: first.location + last.location,
+            });
+        }
+    }
+
+    /// warns about variables from before the branches that only some of them assign
+    fn warn_partial_assignments(&mut self, branches: &[&[Expression]]) {
+        let assigned: Vec<Vec<&Ident>> = branches
+            .iter()
+            // a branch that jumps away never reaches the code after them
+            .filter(|body| !body.last().is_some_and(jumps_away))
+            .map(|body| {
+                let mut idents = vec![];
+                body.iter()
+                    .for_each(|statement| collect_assigned(statement, &mut idents));
+                idents
+            })
+            .collect();
+        let mut warned: Vec<&String> = vec![];
+        for ident in assigned.iter().flatten() {
+            if warned.contains(&&ident.symbol) || self.get_var_noerror(&ident.symbol).is_none() {
+                continue;
+            }
+            let everywhere = assigned
+                .iter()
+                .all(|idents| idents.iter().any(|other| other.symbol == ident.symbol));
+            if !everywhere {
+                warned.push(&ident.symbol);
+                self.warn(Warning {
+                    typ: WarningType::PartialAssignment(ident.symbol.clone()),
+                    location: ident.location,
+                });
+            }
+        }
+    }
+
+    /// unrolled code is compiled more than once, so a warning can come up again
+    fn warn(&mut self, warning: Warning) {
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
     /// get slot of a variable
     ///
     /// # Errors
@@ -168,7 +482,7 @@ impl Compiler {
 
     #[must_use]
     pub fn get_var_noerror(&self, symbol: &String) -> Option<u8> {
-        for scope in self.scopes.iter().rev() {
+        for scope in self.visible_scopes() {
             let entry = scope.variables.get(symbol);
             if let Some(v) = entry {
                 return Some(*v);
@@ -177,19 +491,31 @@ impl Compiler {
         None
     }
 
+    /// inserts a jump mark pointing at the next instruction
+    pub fn mark_here(&mut self) -> u8 {
+        let id = self.insert_jump_mark();
+        self.place_jump_mark(id);
+        id
+    }
+
+    /// points an existing jump mark at the next instruction
+    pub fn place_jump_mark(&mut self, id: u8) {
+        self.jump_marks.insert(id, self.current_address());
+    }
+
     /// Inserts a temporary variable
     ///
     /// # Errors
     ///
     /// When there are too many variables
     pub fn insert_temp_var(&mut self, location: Range) -> Res<u8> {
-        self.get_next_available_slot().ok_or(Error {
+        self.get_last_available_slot().ok_or(Error {
             typ: Box::new(ErrorType::TooManyVars),
             location,
         })
     }
 
-    pub fn cleanup_temp_var(&mut self, index: u8) {
+    pub const fn cleanup_temp_var(&mut self, index: u8) {
         self.variables[index as usize] = false;
     }
 
@@ -200,21 +526,103 @@ impl Compiler {
         last_scope.instructions.push(Instr::Code(instr));
     }
 
-    fn get_instructions(mut self) -> Vec<Instruction> {
+    fn get_instructions(mut self) -> Res<Vec<Instruction>> {
         self.main_scope
             .push(Instr::Scope(self.scopes.split_off_first().0.instructions));
         let mut instructions = vec![];
         Self::flatten_scope(self.main_scope, &mut instructions);
+        Self::check_jump_marks(&instructions, self.jump_mark_count, self.jump_mark_overflow)?;
+        for pass in self.options.passes() {
+            let run = match pass {
+                Pass::RemoveUnreachable => Self::remove_unreachable,
+                Pass::RemoveJumpsToNext => Self::remove_jumps_to_next,
+                Pass::RemoveRedundantLoads => Self::remove_redundant_loads,
+                // these run while the statements are lowered
+                Pass::ConstantFolding | Pass::StrengthReduction | Pass::CommonSubexpressions => {
+                    continue
+                }
+            };
+            run(&mut instructions, &mut self.jump_marks);
+        }
+        Self::check_size(&instructions)?;
+        Self::merge_jump_marks(&mut instructions, &mut self.jump_marks);
         Self::insert_disc_jumps(&mut instructions, &mut self.jump_marks);
-        Self::replace_jump_marks(&mut instructions, &self.jump_marks);
-        instructions
+        Self::check_size(&instructions)?;
+        Self::replace_jump_marks(&mut instructions, &self.jump_marks)?;
+        Ok(instructions)
+    }
+
+    /// errors at the instruction that needed the first jump mark without an id
+    fn check_jump_marks(
+        instructions: &[Instruction],
+        count: usize,
+        overflow: Option<usize>,
+    ) -> Res {
+        let Some(address) = overflow else {
+            return Ok(());
+        };
+        let location = instructions
+            .get(address)
+            .or_else(|| instructions.last())
+            .map_or_else(Range::default, |instr| instr.orig_location);
+        err!(ErrorType::TooManyJumpMarks(count), location)
+    }
+
+    /// errors at the first instruction that doesn't fit into the ROM
+    fn check_size(instructions: &[Instruction]) -> Res {
+        let Some(instr) = instructions.get(MAX_INSTRUCTIONS) else {
+            return Ok(());
+        };
+        err!(
+            ErrorType::ProgramTooLarge(instructions.len()),
+            instr.orig_location
+        )
     }
 
     fn flatten_scope(scope: Vec<Instr>, into: &mut Vec<Instruction>) {
-        scope.into_iter().for_each(|i| match i {
-            Instr::Code(instr) => into.push(instr),
-            Instr::Scope(s) => Self::flatten_scope(s, into),
-        });
+        for i in scope {
+            match i {
+                Instr::Code(instr) => into.push(instr),
+                Instr::Scope(s) => Self::flatten_scope(s, into),
+            }
+        }
+    }
+
+    /// collects the symbols of the top-level scope, has to be called
+    /// before [`Self::get_instructions`]
+    #[must_use]
+    pub fn symbols(&self) -> Symbols {
+        let scope = self.scopes.first();
+        let mut variables: Vec<_> = scope
+            .variables
+            .iter()
+            .map(|(name, slot)| (name.clone(), *slot))
+            .collect();
+        variables.sort_by_key(|(_, slot)| *slot);
+        let mut inline_variables: Vec<_> = scope
+            .inline_variables
+            .iter()
+            .map(|(name, value)| (name.clone(), *value))
+            .collect();
+        inline_variables.sort();
+        let mut arrays: Vec<_> = scope
+            .arrays
+            .iter()
+            .map(|(name, array)| (name.clone(), array.base, array.size))
+            .collect();
+        arrays.sort_by_key(|(_, base, _)| *base);
+        let mut module_slots: Vec<_> = self
+            .module_state
+            .iter()
+            .filter_map(|(key, value)| Some((*key, *value.downcast_ref::<u8>()?)))
+            .collect();
+        module_slots.sort_by_key(|(_, slot)| *slot);
+        Symbols {
+            variables,
+            inline_variables,
+            arrays,
+            module_slots,
+        }
     }
 
     #[must_use]
@@ -230,177 +638,723 @@ impl Compiler {
         self.scopes.len() == 1
     }
 
-    fn generate_assembly(mut self, body: Vec<Expression>) -> Res<Vec<Instruction>, Vec<Error>> {
-        let errors = body
+    fn generate_assembly(mut self, body: Vec<Expression>) -> Res<Compilation, Vec<Error>> {
+        self.eval_program(body)?;
+        let unread = std::mem::take(&mut self.last_scope_mut().unread);
+        self.warn_unread(unread);
+        let warnings = std::mem::take(&mut self.warnings);
+        let symbols = self.symbols();
+        let statements = std::mem::take(&mut self.statements);
+        let instructions = self.get_instructions().map_err(|err| vec![err])?;
+        let statement_costs = statements
             .into_iter()
-            .filter_map(|line| self.eval_statement(line).err())
-            .collect::<Vec<_>>();
+            .map(|location| {
+                let count = instructions
+                    .iter()
+                    .filter(|instr| location.contains(instr.orig_location.0))
+                    .count();
+                (location, count)
+            })
+            .collect();
+        Ok(Compilation {
+            instructions,
+            warnings,
+            symbols,
+            statement_costs,
+        })
+    }
+
+    fn eval_program(&mut self, body: Vec<Expression>) -> Res<(), Vec<Error>> {
+        self.warn_unreachable(&body);
+        let mut errors = vec![];
+        // functions can be called above their definition
+        for line in &body {
+            if let ExpressionType::FunctionDefinition {
+                ident,
+                params,
+                body,
+            } = &line.typ
+            {
+                if let Err(err) = self.define_function(ident.clone(), params.clone(), body.clone())
+                {
+                    errors.push(err);
+                }
+            }
+        }
+        for line in body {
+            self.statements.push(line.location);
+            if matches!(line.typ, ExpressionType::FunctionDefinition { .. }) {
+                continue;
+            }
+            if let Err(err) = self.eval_statement(line) {
+                errors.push(err);
+            }
+        }
+        if self.traced {
+            errors.extend(self.trace_slot_writes.iter().map(|&location| Error {
+                typ: Box::new(ErrorType::TraceSlotWritten(TRACE_SLOT)),
+                location,
+            }));
+        }
         if !errors.is_empty() {
             return Err(errors);
         }
-
-        Ok(self.get_instructions())
+        Ok(())
     }
 
-    fn insert_jump_mark(&mut self) -> u8 {
-        let id = self.jump_marks.len() as u8;
+    /// ids past [`MAX_JUMP_MARKS`] wrap around, [`Self::check_jump_marks`]
+    /// reports them once the program is done
+    pub fn insert_jump_mark(&mut self) -> u8 {
+        if self.jump_mark_count == MAX_JUMP_MARKS {
+            self.jump_mark_overflow = Some(self.current_address());
+        }
+        let id = self.jump_mark_count as u8;
+        self.jump_mark_count += 1;
         self.jump_marks.insert(id, 0);
         id
     }
 
+    fn eval_use(&mut self, modules: Vec1<Ident>, alias: Option<Ident>, location: Range) -> Res {
+        if let Some(alias) = alias {
+            if exist(&alias.symbol) {
+                return err!(ErrorType::AliasIsModule(alias.symbol), alias.location);
+            }
+            let module = &modules.first().symbol;
+            if let Some(taken) = self
+                .aliases
+                .get(&alias.symbol)
+                .filter(|taken| *taken != module)
+            {
+                return err!(
+                    ErrorType::AliasTaken {
+                        alias: alias.symbol.clone(),
+                        module: taken.clone(),
+                    },
+                    alias.location
+                );
+            }
+            self.aliases
+                .insert(alias.symbol, modules.first().symbol.clone());
+        }
+        for module in modules {
+            if !self.is_root_scope() {
+                return Err(Error {
+                    typ: Box::new(ErrorType::UseOutsideGlobalScope),
+                    location,
+                });
+            }
+            if !exist(&module.symbol) {
+                return Err(Error {
+                    typ: Box::new(ErrorType::NonexistentModule(module.symbol)),
+                    location,
+                });
+            }
+            init(&module.symbol, self, location)?;
+            self.modules.insert(module.symbol);
+        }
+        Ok(())
+    }
+
     fn eval_statement(&mut self, line: Expression) -> Res {
         match line.typ {
             ExpressionType::InlineDeclaration { ident, value } => {
-                let value = self.try_eval_const(&value).map_err(|loc| Error {
-                    typ: Box::new(ErrorType::ForbiddenInline),
-                    location: loc,
-                })?;
+                let value = self.try_eval_const(&value)?;
                 self.insert_inline_var(ident.symbol, value);
                 Ok(())
             }
-            ExpressionType::Use(modules) => {
-                for module in modules {
-                    if !self.is_root_scope() {
-                        return Err(Error {
-                            typ: Box::new(ErrorType::UseOutsideGlobalScope),
-                            location: line.location,
-                        });
-                    }
-                    if !exist(&module.symbol) {
-                        return Err(Error {
-                            typ: Box::new(ErrorType::NonexistentModule(module.symbol)),
-                            location: line.location,
-                        });
-                    }
-                    init(&module.symbol, self, line.location)?;
-                    self.modules.insert(module.symbol);
-                }
-                Ok(())
-            }
+            ExpressionType::Use { modules, alias } => self.eval_use(modules, alias, line.location),
             ExpressionType::VarDeclaration { ident } => {
-                self.insert_var(&ident.symbol, line.location)?;
+                self.insert_var(&ident.symbol, ident.location)?;
                 Ok(())
             }
+            ExpressionType::ArrayDeclaration { ident, size } => self.declare_array(ident, &size),
             ExpressionType::Pass => Ok(()),
+            ExpressionType::FunctionDefinition {
+                ident,
+                params,
+                body,
+            } => self.define_function(ident, params, body),
+            ExpressionType::Break => {
+                let Some(innermost) = self.loops.last_mut() else {
+                    return err!(ErrorType::OutsideLoop("break".to_string()), line.location);
+                };
+                innermost.broken = true;
+                let end = innermost.end;
+                instr!(self, JMP, end, line.location);
+                Ok(())
+            }
+            ExpressionType::Continue => {
+                let Some(innermost) = self.loops.last_mut() else {
+                    return err!(
+                        ErrorType::OutsideLoop("continue".to_string()),
+                        line.location
+                    );
+                };
+                innermost.continued = true;
+                let next = innermost.next;
+                instr!(self, JMP, next, line.location);
+                Ok(())
+            }
+            ExpressionType::Stop => {
+                instr!(self, STOP, line.location);
+                Ok(())
+            }
             ExpressionType::EndlessLoop { body } => {
-                let mark = Self::scope_len(&self.scopes.first().instructions);
-                let id = self.insert_jump_mark();
-                self.jump_marks.insert(id, mark);
+                self.in_loop(|this| this.eval_forever(body, line.location))
+            }
+            ExpressionType::WhileLoop { condition, body } => {
+                self.eval_while_loop(&condition, body, line.location)
+            }
+            ExpressionType::DoWhile { body, condition } => {
+                self.in_loop(|this| this.eval_do_while(body, &condition))
+            }
+            ExpressionType::Trace(tag) => {
+                let Some(value) = self.try_get_constant(&tag) else {
+                    return err!(
+                        ErrorType::CompileTimeArg("The trace tag".to_string()),
+                        tag.location
+                    );
+                };
+                self.put_a_number(value, line.location);
+                self.save_to_out(TRACE_SLOT, line.location);
+                self.traced = true;
+                Ok(())
+            }
+            ExpressionType::Assert(condition) => self.eval_assert(*condition),
+            ExpressionType::Repeat { count, index, body } => {
+                self.in_loop(|this| this.eval_repeat(&count, index.as_ref(), body, line.location))
+            }
+            ExpressionType::Conditional {
+                condition,
+                body,
+                paths,
+                alternate,
+            } => self.eval_conditional(*condition, body, paths, alternate),
+            ExpressionType::Match {
+                value,
+                arms,
+                alternate,
+            } => self.eval_match(&value, arms, alternate),
+            ExpressionType::Call { function, args } => match &function.typ {
+                ExpressionType::Identifier(name) => {
+                    self.eval_function_call(name, &args, function.location)
+                }
+                _ => self.eval_call(&function, &args),
+            },
+            _ => self.eval_expr(&line),
+        }?;
+        Ok(())
+    }
 
-                self.push_scope(body, ComputerState::default())?;
-                self.pop_scope();
+    /// checks the condition at compile-time, it doesn't emit any instructions
+    fn eval_assert(&mut self, condition: Expression) -> Res {
+        let location = condition.location;
+        let (left, right, operator) = eval_condition(condition)?;
+        for side in [&left, &right] {
+            if self.try_get_constant(side).is_none() {
+                return err!(
+                    ErrorType::CompileTimeArg("The assertion".to_string()),
+                    side.location
+                );
+            }
+        }
+        if self.const_condition(&left, &right, operator) == Some(false) {
+            return err!(AssertionFailed, location);
+        }
+        Ok(())
+    }
 
-                instr!(self, JMP, id, line.location);
+    /// compiles a loop, so `break` can jump to the mark after it and
+    /// `continue` to the one placed with [`Compiler::place_continue`]
+    fn in_loop(&mut self, eval: impl FnOnce(&mut Self) -> Res) -> Res {
+        let end = self.insert_jump_mark();
+        let next = self.insert_jump_mark();
+        self.loops.push(Loop {
+            end,
+            next,
+            broken: false,
+            continued: false,
+        });
+        let result = eval(self);
+        let innermost = self.loops.pop().expect("Loop was pushed above");
+        self.jump_marks.insert(end, self.current_address());
+        // a break can arrive with any register contents
+        if innermost.broken {
+            self.last_scope_mut().state = ComputerState::UNKNOWN;
+        }
+        result
+    }
 
-                Ok(())
-            }
-            ExpressionType::WhileLoop { condition, body } => {
-                let (left, right, operator) = eval_condition(*condition)?;
+    /// where a `continue` of the innermost loop lands
+    fn place_continue(&mut self) {
+        let innermost = self.loops.last().expect("Called in a loop");
+        let (next, continued) = (innermost.next, innermost.continued);
+        self.jump_marks.insert(next, self.current_address());
+        if continued {
+            self.last_scope_mut().state = ComputerState::UNKNOWN;
+        }
+    }
 
-                let start_id = self.insert_jump_mark();
-                let end_id = self.insert_jump_mark();
+    fn eval_forever(&mut self, body: Vec<Expression>, location: Range) -> Res {
+        let id = self.loops.last().expect("Called in a loop").next;
+        self.jump_marks.insert(id, self.current_address());
 
-                self.put_comparison((&left, &right, operator.opposite()), line.location, end_id)?;
+        // the back-edge arrives with whatever the body left behind
+        self.push_scope(body, ComputerState::UNKNOWN)?;
+        self.pop_scope();
 
-                let start = Self::scope_len(&self.scopes.first().instructions);
+        instr!(self, JMP, id, location);
 
-                self.jump_marks.insert(start_id, start);
+        Ok(())
+    }
 
-                self.push_scope(body, self.last_scope().state)?;
+    fn eval_while_loop(
+        &mut self,
+        condition: &Expression,
+        body: Vec<Expression>,
+        location: Range,
+    ) -> Res {
+        // a constant condition either drops the loop or makes it a `forever`
+        match self.const_logical(condition) {
+            Some(true) => self.in_loop(|this| this.eval_forever(body, location)),
+            Some(false) => Ok(()),
+            None => self.in_loop(|this| match &condition.typ {
+                ExpressionType::EqExpr {
+                    left,
+                    right,
+                    operator,
+                } => this.eval_while((left, right, *operator), body, location),
+                _ => this.eval_logical_while(condition, body),
+            }),
+        }
+    }
 
-                self.put_comparison((&left, &right, operator), line.location, start_id)?;
+    fn eval_while(
+        &mut self,
+        condition: (&Expression, &Expression, EqualityOperator),
+        body: Vec<Expression>,
+        location: Range,
+    ) -> Res {
+        let (left, right, operator) = condition;
 
-                self.pop_scope();
-                let end = Self::scope_len(&self.scopes.first().instructions);
+        let start_id = self.insert_jump_mark();
+        let end_id = self.loops.last().expect("Called in a loop").end;
 
-                self.jump_marks.insert(end_id, end);
+        let swapped =
+            self.put_comparison((left, right, operator.opposite()), location, end_id, false)?;
 
-                Ok(())
+        let start = self.current_address();
+
+        self.jump_marks.insert(start_id, start);
+
+        // the back-edge comparison loads A and B in the same order as this one,
+        // so the body can start out knowing their contents
+        let state = self.last_scope().state;
+        self.push_scope(
+            body,
+            ComputerState {
+                a: state.a,
+                b: state.b,
+                c: RegisterContents::Unknown,
+                ram_page: RamPage::Unknown,
+            },
+        )?;
+
+        // continue has to re-evaluate the condition
+        self.place_continue();
+        if swapped {
+            self.put_comparison(
+                (right, left, operator.turnaround()),
+                location,
+                start_id,
+                true,
+            )?;
+        } else {
+            self.put_comparison((left, right, operator), location, start_id, true)?;
+        }
+
+        self.pop_scope();
+
+        Ok(())
+    }
+
+    /// a `while` whose condition is combined with `and`/`or`
+    fn eval_logical_while(&mut self, condition: &Expression, body: Vec<Expression>) -> Res {
+        let start_id = self.insert_jump_mark();
+        let end_id = self.loops.last().expect("Called in a loop").end;
+
+        self.put_condition(condition, false, end_id)?;
+        self.jump_marks.insert(start_id, self.current_address());
+
+        // the back-edge can arrive from any of the comparisons
+        self.push_scope(body, ComputerState::UNKNOWN)?;
+        self.place_continue();
+        self.put_condition(condition, true, start_id)?;
+        self.pop_scope();
+
+        self.last_scope_mut().state = ComputerState::UNKNOWN;
+        Ok(())
+    }
+
+    /// the body runs once before the condition is checked
+    fn eval_do_while(&mut self, body: Vec<Expression>, condition: &Expression) -> Res {
+        // the back-edge arrives with different register contents
+        let start_id = self.mark_here();
+        self.push_scope(body, ComputerState::UNKNOWN)?;
+
+        self.place_continue();
+        self.put_condition(condition, true, start_id)?;
+
+        let state = self.pop_scope();
+        self.last_scope_mut().state = state;
+        Ok(())
+    }
+
+    /// Short loops are unrolled, binding the index as an inline variable for
+    /// each iteration. Longer ones count in a temp, which the index names
+    fn eval_repeat(
+        &mut self,
+        count: &Expression,
+        index: Option<&Ident>,
+        body: Vec<Expression>,
+        location: Range,
+    ) -> Res {
+        let Some(count) = self.try_get_constant(count) else {
+            return err!(
+                ErrorType::CompileTimeArg("The repeat count".to_string()),
+                count.location
+            );
+        };
+
+        let unroll_limit = if index.is_some() {
+            INDEXED_REPEAT_UNROLL_LIMIT
+        } else {
+            REPEAT_UNROLL_LIMIT
+        };
+        if count <= unroll_limit {
+            for i in 0..count {
+                // every copy of the body continues to its own end
+                let next = self.insert_jump_mark();
+                let innermost = self.loops.last_mut().expect("Called in a loop");
+                innermost.next = next;
+                innermost.continued = false;
+
+                self.scopes.push(Scope::with_state(self.last_scope().state));
+                if let Some(index) = index {
+                    self.insert_inline_var(index.symbol.clone(), i);
+                    self.last_scope_mut().loop_index = Some(index.symbol.clone());
+                }
+                self.warn_unreachable(&body);
+                body.iter()
+                    .try_for_each(|line| self.eval_statement(line.clone()))?;
+                self.place_continue();
+                let state = self.pop_scope();
+                self.last_scope_mut().state = state;
             }
-            ExpressionType::Conditional {
-                condition,
-                body,
-                paths,
-                alternate,
-            } => self.eval_conditional(*condition, body, paths, alternate)?,
-            _ => self.eval_expr(&line),
-        }?;
+            return Ok(());
+        }
+
+        // an index counts up from 0, otherwise the counter counts down to 0
+        let (first, step, last, jump) = if index.is_some() {
+            (0, InstructionVariant::ADD, count, InstructionVariant::JL)
+        } else {
+            (count, InstructionVariant::SUB, 0, InstructionVariant::JNE)
+        };
+        let counter = self.insert_temp_var(location)?;
+        self.put_a_number(first, location);
+        self.save_to(counter, location);
+
+        let start_id = self.insert_jump_mark();
+        self.jump_marks.insert(start_id, self.current_address());
+
+        // the back-edge arrives with whatever the body left behind
+        self.scopes.push(Scope::with_state(ComputerState::UNKNOWN));
+        if let Some(index) = index {
+            let scope = self.last_scope_mut();
+            scope.variables.insert(index.symbol.clone(), counter);
+            scope.loop_index = Some(index.symbol.clone());
+        }
+        self.warn_unreachable(&body);
+        body.into_iter()
+            .try_for_each(|line| self.eval_statement(line))?;
+
+        self.place_continue();
+        instr!(self, LA, counter, location);
+        self.put_b_number(1, location);
+        self.push_instr(Instruction::new(step, None, location));
+        self.save_to(counter, location);
+        self.put_b_number(last, location);
+        self.push_instr(Instruction::new(jump, Some(start_id), location));
+
+        let state = self.pop_scope();
+        self.last_scope_mut().state = state;
+        self.cleanup_temp_var(counter);
         Ok(())
     }
 
+    /// Branches with a constant condition are dropped if it never holds,
+    /// and become the `else` if it always does
     fn eval_conditional(
         &mut self,
         condition: Expression,
         body: Vec<Expression>,
         paths: Vec<(Expression, Vec<Expression>)>,
-        alternate: Option<Vec<Expression>>,
-    ) -> Result<Result<(), Error>, Error> {
-        let location = condition.location;
-        let (left, right, operator) = eval_condition(condition)?;
+        mut alternate: Option<Vec<Expression>>,
+    ) -> Res {
+        if let Some(alternate) = &alternate {
+            let bodies: Vec<&[Expression]> = std::iter::once(body.as_slice())
+                .chain(paths.iter().map(|(_, body)| body.as_slice()))
+                .chain(std::iter::once(alternate.as_slice()))
+                .collect();
+            self.warn_partial_assignments(&bodies);
+        }
+
         let end_id = self.insert_jump_mark();
-        let mut next_mark_id = self.insert_jump_mark();
 
-        self.put_comparison((&left, &right, operator.opposite()), location, next_mark_id)?;
+        let mut branches = vec![];
+        for (condition, body) in std::iter::once((condition, body)).chain(paths) {
+            match self.const_logical(&condition) {
+                Some(false) => {}
+                Some(true) => {
+                    alternate = Some(body);
+                    break;
+                }
+                None => branches.push((condition, body)),
+            }
+        }
 
         let mut last_state = self.last_scope().state;
+        // what all the paths arriving at the end agree on
+        let mut end_state: Option<ComputerState> = None;
+        let mut arrive = |state: ComputerState| {
+            end_state = Some(end_state.map_or(state, |end| end.merge(state)));
+        };
+        let branch_count = branches.len();
+        for (index, (condition, body)) in branches.into_iter().enumerate() {
+            let next_mark_id = self.insert_jump_mark();
+
+            self.put_condition(&condition, false, next_mark_id)?;
 
-        self.push_scope(body, last_state)?;
-        if !paths.is_empty() || alternate.is_some() {
-            instr!(self, JMP, end_id, location);
+            let state = self.last_scope().state;
+            last_state = self.jump_target_state(&condition);
+            self.last_scope_mut().state = last_state;
+
+            self.push_scope(body, state)?;
+            if index != branch_count - 1 || alternate.is_some() {
+                instr!(self, JMP, end_id, condition.location);
+            }
+            arrive(self.pop_scope());
+            self.jump_marks.insert(next_mark_id, self.current_address());
         }
-        self.pop_scope();
-        self.jump_marks.insert(
-            next_mark_id,
-            Self::scope_len(&self.scopes.first().instructions),
-        );
-        let path_len = paths.len();
-        paths.into_iter().enumerate().try_for_each(|path| {
-            let (index, (condition, body)) = path;
-            let location = condition.location;
-            let (left, right, operator) = eval_condition(condition)?;
+        if let Some(body) = alternate {
+            self.push_scope(body, last_state)?;
+            arrive(self.pop_scope());
+        } else if branch_count != 0 {
+            // none of the conditions held
+            arrive(last_state);
+        }
+        if let Some(state) = end_state {
+            self.last_scope_mut().state = state;
+        }
+        self.jump_marks.insert(end_id, self.current_address());
+        Ok(())
+    }
 
-            next_mark_id = self.insert_jump_mark();
+    /// compares the value with every case and jumps to the first one that
+    /// is equal, without a match the `else` arm runs
+    fn eval_match(
+        &mut self,
+        value: &Expression,
+        mut arms: Vec<(Expression, Vec<Expression>)>,
+        alternate: Option<Vec<Expression>>,
+    ) -> Res {
+        // with everything known the arm is picked at compile-time
+        let folding = self.options.runs(Pass::ConstantFolding);
+        if let Some(known) = self.try_get_constant(value).filter(|_| folding) {
+            let cases: Option<Vec<i16>> = arms
+                .iter()
+                .map(|(case, _)| self.try_get_constant(case))
+                .collect();
+            if let Some(cases) = cases {
+                let body = cases
+                    .iter()
+                    .position(|case| *case == known)
+                    .map_or(alternate, |index| Some(arms.swap_remove(index).1));
+                if let Some(body) = body {
+                    let state = self.last_scope().state;
+                    self.push_scope(body, state)?;
+                    let state = self.pop_scope();
+                    self.last_scope_mut().state = state;
+                }
+                return Ok(());
+            }
+        }
 
-            self.put_comparison((&left, &right, operator.opposite()), location, next_mark_id)?;
+        self.eval_expr(value)?;
+        let end_id = self.insert_jump_mark();
 
-            last_state = self.last_scope().state;
+        let mut targets = vec![];
+        for (case, body) in arms {
+            if let Some(number) = self.try_get_constant(&case) {
+                self.put_b_number(number, case.location);
+            } else if Self::can_put_into_b(&case) {
+                self.put_into_b(&case)?;
+            } else {
+                return err!(
+                    ErrorType::CompileTimeArg("The case value".to_string()),
+                    case.location
+                );
+            }
+            let arm_id = self.insert_jump_mark();
+            instr!(self, JE, arm_id, case.location);
+            targets.push((arm_id, self.last_scope().state, body, case.location));
+        }
 
-            self.push_scope(body, last_state)?;
+        let mut end_state = self.last_scope().state;
+        if let Some(body) = alternate {
+            self.push_scope(body, end_state)?;
+            instr!(self, JMP, end_id, value.location);
+            end_state = self.pop_scope();
+        } else {
+            instr!(self, JMP, end_id, value.location);
+        }
 
-            if index != path_len - 1 || alternate.is_some() {
+        let arm_count = targets.len();
+        for (index, (arm_id, state, body, location)) in targets.into_iter().enumerate() {
+            self.jump_marks.insert(arm_id, self.current_address());
+            self.push_scope(body, state)?;
+            if index != arm_count - 1 {
                 instr!(self, JMP, end_id, location);
             }
+            end_state = end_state.merge(self.pop_scope());
+        }
+
+        self.last_scope_mut().state = end_state;
+        self.jump_marks.insert(end_id, self.current_address());
+        Ok(())
+    }
+
+    /// # Returns
+    /// the outcome of the comparison if both sides are known at compile-time
+    fn const_condition(
+        &mut self,
+        left: &Expression,
+        right: &Expression,
+        operator: EqualityOperator,
+    ) -> Option<bool> {
+        let left = self.try_get_constant(left)?;
+        let right = self.try_get_constant(right)?;
+        Some(operator.holds(left, right))
+    }
+
+    /// # Returns
+    /// the outcome of the condition if it is known at compile-time
+    ///
+    /// only a known left side can short-circuit, the right one is
+    /// never skipped if the left one has to be evaluated.
+    /// Without constant folding nothing is known, so the comparisons stay
+    fn const_logical(&mut self, condition: &Expression) -> Option<bool> {
+        if !self.options.runs(Pass::ConstantFolding) {
+            return None;
+        }
+        match &condition.typ {
+            ExpressionType::EqExpr {
+                left,
+                right,
+                operator,
+            } => self.const_condition(left, right, *operator),
+            ExpressionType::LogicalExpr {
+                left,
+                right,
+                operator,
+            } => {
+                let short = *operator == LogicalOperator::Or;
+                if self.const_logical(left)? == short {
+                    Some(short)
+                } else {
+                    self.const_logical(right)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// jumps to `jump_to` if the condition evaluates to `when`
+    fn put_condition(&mut self, condition: &Expression, when: bool, jump_to: u8) -> Res {
+        if let Some(holds) = self.const_logical(condition) {
+            if holds == when {
+                instr!(self, JMP, jump_to, condition.location);
+            }
+            return Ok(());
+        }
+        match &condition.typ {
+            ExpressionType::EqExpr {
+                left,
+                right,
+                operator,
+            } => {
+                let operator = if when { *operator } else { operator.opposite() };
+                self.put_comparison((left, right, operator), condition.location, jump_to, false)?;
+            }
+            ExpressionType::LogicalExpr {
+                left,
+                right,
+                operator,
+            } => {
+                // `and` is decided by a false side, `or` by a true one
+                let short = *operator == LogicalOperator::Or;
+                // a known side that doesn't decide the outcome can be left out
+                if self.const_logical(left) == Some(!short) {
+                    return self.put_condition(right, when, jump_to);
+                }
+                if self.const_logical(right) == Some(!short) {
+                    return self.put_condition(left, when, jump_to);
+                }
+                if when == short {
+                    self.put_condition(left, when, jump_to)?;
+                    self.put_condition(right, when, jump_to)?;
+                } else {
+                    let skip_id = self.insert_jump_mark();
+                    self.put_condition(left, short, skip_id)?;
+                    self.put_condition(right, when, jump_to)?;
+                    self.jump_marks.insert(skip_id, self.current_address());
+                    self.last_scope_mut().state = ComputerState::UNKNOWN;
+                }
+            }
+            _ => return err!(NormalInEqExpr, condition.location),
+        }
+        Ok(())
+    }
 
-            self.pop_scope();
-            self.jump_marks.insert(
-                next_mark_id,
-                Self::scope_len(&self.scopes.first().instructions),
-            );
-
-            Ok(())
-        })?;
-        if let Some(body) = alternate {
-            self.push_scope(body, last_state)?;
-            self.pop_scope();
+    /// # Returns
+    /// the state where the jumps of [`Compiler::put_condition`] land,
+    /// a combined condition can jump there from any of its comparisons
+    fn jump_target_state(&self, condition: &Expression) -> ComputerState {
+        if matches!(condition.typ, ExpressionType::LogicalExpr { .. }) {
+            ComputerState::UNKNOWN
+        } else {
+            self.last_scope().state
         }
-        self.jump_marks
-            .insert(end_id, Self::scope_len(&self.scopes.first().instructions));
-        Ok(Ok(()))
     }
 
-    fn pop_scope(&mut self) {
+    /// # Returns
+    /// the state the popped scope ended with
+    fn pop_scope(&mut self) -> ComputerState {
         let scope = self.scopes.pop().unwrap();
         self.last_scope_mut()
             .instructions
             .push(Instr::Scope(scope.instructions));
+        self.warn_unread(scope.unread);
         for i in scope.variables {
             let (_, slot) = i;
             self.variables[slot as usize] = false;
         }
+        scope.state
     }
 
     fn push_scope(&mut self, body: Vec<Expression>, state: ComputerState) -> Res {
         self.scopes.push(Scope::with_state(state));
+        self.warn_unreachable(&body);
         body.into_iter()
             .try_for_each(|line| self.eval_statement(line))?;
         Ok(())
@@ -411,9 +1365,11 @@ impl Compiler {
         condition: (&Expression, &Expression, EqualityOperator),
         location: Range,
         jump_to: u8,
-    ) -> Res {
+        keep_order: bool,
+    ) -> Res<bool> {
         let (left, right, operator) = condition;
-        let op = if self.put_ab(left, right, true)? {
+        let swapped = self.put_ab(left, right, !keep_order)?;
+        let op = if swapped {
             operator.turnaround()
         } else {
             operator
@@ -423,32 +1379,72 @@ impl Compiler {
             Some(jump_to),
             location,
         ));
+        Ok(swapped)
+    }
+
+    /// puts 1 into A if the condition holds and 0 otherwise
+    fn eval_comparison(&mut self, condition: &Expression) -> Res {
+        let location = condition.location;
+        if let Some(holds) = self.const_logical(condition) {
+            self.put_a_number(i16::from(holds), location);
+            return Ok(());
+        }
+
+        let false_id = self.insert_jump_mark();
+        let end_id = self.insert_jump_mark();
+        self.put_condition(condition, false, false_id)?;
+        let state = self.jump_target_state(condition);
+
+        self.put_a_number(1, location);
+        instr!(self, JMP, end_id, location);
+
+        self.jump_marks.insert(false_id, self.current_address());
+        self.last_scope_mut().state = state;
+        self.put_a_number(0, location);
+
+        self.jump_marks.insert(end_id, self.current_address());
+        self.last_scope_mut().state.a = RegisterContents::Unknown;
         Ok(())
     }
 
-    fn try_eval_const(&mut self, expr: &Expression) -> Result<i16, Range> {
+    /// # Errors
+    ///
+    /// if the expression isn't constant or overflows
+    fn try_eval_const(&mut self, expr: &Expression) -> Res<i16> {
         match &expr.typ {
-            ExpressionType::Identifier(name) => self
-                .get_inline_var(name, expr.location)
-                .map_err(|e| e.location),
+            ExpressionType::Identifier(name) => {
+                self.get_inline_var(name, expr.location).map_err(|_| Error {
+                    typ: Box::new(ErrorType::ForbiddenInline),
+                    location: expr.location,
+                })
+            }
             ExpressionType::BinaryExpr {
                 left,
                 right,
                 operator,
             } => {
-                let left = self.try_eval_const(left)?;
-                let right = self.try_eval_const(right)?;
-                Ok(match operator {
-                    Operator::Plus => left + right,
-                    Operator::Minus => left - right,
-                    Operator::Mult => left * right,
-                    Operator::And => left & right,
-                    Operator::Or => left | right,
-                    Operator::Xor => left ^ right,
-                })
+                let left_value = self.try_eval_const(left)?;
+                let right_value = self.try_eval_const(right)?;
+                let result = match operator {
+                    Operator::Plus => left_value.checked_add(right_value),
+                    Operator::Minus => left_value.checked_sub(right_value),
+                    Operator::Mult => left_value.checked_mul(right_value),
+                    // wraps like the MULs it stands for
+                    Operator::Pow => Some(
+                        u32::try_from(right_value)
+                            .map_or(1, |exponent| left_value.wrapping_pow(exponent)),
+                    ),
+                    Operator::And => Some(left_value & right_value),
+                    Operator::Or => Some(left_value | right_value),
+                    Operator::Xor => Some(left_value ^ right_value),
+                };
+                result.map_or_else(|| err!(InlineOverflow, left.location + right.location), Ok)
             }
             ExpressionType::NumericLiteral(value) => Ok(*value),
-            _ => Err(expr.location),
+            ExpressionType::Member { object, property } => self
+                .module_constant(object, property)
+                .map_or_else(|| err!(ForbiddenInline, expr.location), Ok),
+            _ => err!(ForbiddenInline, expr.location),
         }
     }
 
@@ -458,15 +1454,21 @@ impl Compiler {
     /// on any compiler error
     pub fn eval_expr(&mut self, expr: &Expression) -> Res {
         match &expr.typ {
-            ExpressionType::NumericLiteral(..) => self.put_into_a(expr)?,
-            ExpressionType::Identifier(..) => self.put_into_a(expr)?,
+            ExpressionType::NumericLiteral(..) | ExpressionType::Identifier(..) => {
+                self.put_into_a(expr)?;
+            }
             ExpressionType::BinaryExpr {
                 left,
                 right,
                 operator,
-            } => self.eval_binary_expr(left, right, *operator, expr.location)?,
+            } => match self.try_eval_const(expr) {
+                Ok(value) if self.options.runs(Pass::ConstantFolding) => {
+                    self.put_a_number(value, expr.location);
+                }
+                _ => self.eval_binary_expr(left, right, *operator, expr.location)?,
+            },
             ExpressionType::Assignment { ident, value } => {
-                self.eval_assignment(&ident.symbol, value)?;
+                self.eval_assignment(ident, value)?;
             }
             ExpressionType::IAssignment {
                 ident,
@@ -475,12 +1477,47 @@ impl Compiler {
             } => {
                 self.eval_iassignment(ident, value, *operator)?;
             }
+            ExpressionType::Increment { ident, operator } => {
+                self.eval_increment(ident, *operator, expr.location)?;
+            }
             ExpressionType::Call { args, function } => self.eval_call(function, args)?,
-            ExpressionType::EqExpr { .. } => {
-                return err!(EqInNormalExpr, expr.location);
+            ExpressionType::Index { array, index } => {
+                let address = self.array_address(array, index)?;
+                self.call_ram("read", &[address], expr.location)?;
+            }
+            ExpressionType::IndexAssignment {
+                array,
+                index,
+                value,
+            } => {
+                let address = self.array_address(array, index)?;
+                self.call_ram("write", &[(**value).clone(), address], expr.location)?;
+            }
+            ExpressionType::EqExpr { .. } | ExpressionType::LogicalExpr { .. } => {
+                self.eval_comparison(expr)?;
+            }
+            ExpressionType::Debug(value) => {
+                // the marker itself loads A, so the value is shown in B
+                if let Some(value) = value {
+                    if Self::can_put_into_b(value) {
+                        self.put_into_b(value)?;
+                    } else {
+                        self.eval_expr(value)?;
+                        let temp = self.insert_temp_var(expr.location)?;
+                        instr!(self, SVA, temp, expr.location);
+                        instr!(self, LB, temp, expr.location);
+                        self.cleanup_temp_var(temp);
+                    }
+                }
+                instr!(self, LAL, 17, expr.location);
+            }
+            ExpressionType::ArrayLiteral(..) => return err!(MisplacedArrayLiteral, expr.location),
+            ExpressionType::Member { object, property } => {
+                let Some(value) = self.module_constant(object, property) else {
+                    return err!(NoConstants, expr.location);
+                };
+                self.put_a_number(value, expr.location);
             }
-            ExpressionType::Debug => instr!(self, LAL, 17, expr.location),
-            ExpressionType::Member { .. } => return err!(NoConstants, expr.location),
             _ => todo!("unsupported expression: {:?}", expr),
         }
         Ok(())
@@ -509,12 +1546,167 @@ impl Compiler {
         operator: Operator,
         location: Range,
     ) -> Res {
+        if operator == Operator::Pow {
+            return self.eval_power(left, right, location);
+        }
+        let reduce = self.options.runs(Pass::StrengthReduction);
+        if reduce && operator == Operator::Plus {
+            if let Some((symbol, count)) = self
+                .self_addition(left)
+                .zip(self.self_addition(right))
+                .filter(|((left, _), (right, _))| left == right)
+                .map(|((symbol, left), (_, right))| (symbol, left + right))
+            {
+                return self.put_self_addition(symbol, count, location);
+            }
+        }
+        if reduce && operator == Operator::Mult {
+            if let Some((factor, shift)) = self
+                .power_of_two(right)
+                .map(|shift| (left, shift))
+                .or_else(|| self.power_of_two(left).map(|shift| (right, shift)))
+            {
+                self.eval_expr(factor)?;
+                if shift != 0 {
+                    instr!(self, SUP, shift, location);
+                }
+                return Ok(());
+            }
+        }
+
         self.put_ab(left, right, operator.is_commutative())?;
 
         self.put_op(operator, location);
         Ok(())
     }
 
+    /// # Returns
+    /// the variable and how often it is added to itself, if `expr` only adds
+    /// a single variable to itself
+    fn self_addition<'a>(&self, expr: &'a Expression) -> Option<(&'a String, u32)> {
+        match &expr.typ {
+            ExpressionType::Identifier(symbol) => self.get_var_noerror(symbol).map(|_| (symbol, 1)),
+            ExpressionType::BinaryExpr {
+                left,
+                right,
+                operator: Operator::Plus,
+            } => {
+                let (symbol, left) = self.self_addition(left)?;
+                let (other, right) = self.self_addition(right)?;
+                (symbol == other).then_some((symbol, left + right))
+            }
+            _ => None,
+        }
+    }
+
+    /// # Returns
+    /// the log2 of `expr` if it is a constant power of two, so a
+    /// multiplication with it can be a shift instead of a `MUL`
+    fn power_of_two(&mut self, expr: &Expression) -> Option<u8> {
+        self.try_get_constant(expr)
+            .filter(|value| *value > 0 && value.count_ones() == 1)
+            .map(|value| value.trailing_zeros() as u8)
+    }
+
+    /// `x + x + ...` as a shift, adding the rest if `count` isn't a power of two
+    fn put_self_addition(&mut self, symbol: &str, count: u32, location: Range) -> Res {
+        let var = Expression {
+            typ: ExpressionType::Identifier(symbol.to_owned()),
+            location,
+        };
+        let shift = count.ilog2();
+        self.put_into_a(&var)?;
+        instr!(self, SUP, shift as u8, location);
+        for _ in 0..count - (1 << shift) {
+            self.put_into_b(&var)?;
+            instr!(self, ADD, location);
+        }
+        Ok(())
+    }
+
+    fn eval_power(&mut self, base: &Expression, exponent: &Expression, location: Range) -> Res {
+        match self.try_get_constant(exponent) {
+            Some(exponent) if exponent < 1 => {
+                if !is_pure(base) {
+                    self.eval_expr(base)?;
+                }
+                self.put_a_number(1, location);
+            }
+            Some(exponent) if exponent <= POW_UNROLL_LIMIT => {
+                self.eval_expr(base)?;
+                if exponent > 1 {
+                    if Self::can_put_into_b(base) {
+                        self.put_into_b(base)?;
+                    } else {
+                        self.switch(location)?;
+                    }
+                }
+                for _ in 1..exponent {
+                    instr!(self, MUL, location);
+                }
+            }
+            _ => self.put_power_loop(base, exponent, location)?,
+        }
+        Ok(())
+    }
+
+    /// multiplies 1 by the base until the exponent is counted down,
+    /// a base that is a power of two is shifted in instead
+    fn put_power_loop(&mut self, base: &Expression, exponent: &Expression, location: Range) -> Res {
+        let shift = self.power_of_two(base);
+        let base_slot = if shift.is_none() && !Self::can_put_into_b(base) {
+            self.eval_expr(base)?;
+            let slot = self.insert_temp_var(location)?;
+            self.save_to(slot, location);
+            Some(slot)
+        } else {
+            None
+        };
+        self.eval_expr(exponent)?;
+        let counter = self.insert_temp_var(location)?;
+        self.save_to(counter, location);
+        let result = self.insert_temp_var(location)?;
+        self.put_a_number(1, location);
+        self.save_to(result, location);
+
+        let start = self.mark_here();
+        self.last_scope_mut().state = ComputerState::UNKNOWN;
+        let end = self.insert_jump_mark();
+        instr!(self, LA, counter, location);
+        self.put_b_number(0, location);
+        instr!(self, JLE, end, location);
+        self.put_b_number(1, location);
+        instr!(self, SUB, location);
+        self.save_to(counter, location);
+
+        instr!(self, LA, result, location);
+        match (shift, base_slot) {
+            (Some(0), _) => {}
+            (Some(shift), _) => instr!(self, SUP, shift, location),
+            (None, Some(slot)) => {
+                instr!(self, LB, slot, location);
+                instr!(self, MUL, location);
+            }
+            (None, None) => {
+                self.put_into_b(base)?;
+                instr!(self, MUL, location);
+            }
+        }
+        self.save_to(result, location);
+        instr!(self, JMP, start, location);
+
+        self.place_jump_mark(end);
+        self.last_scope_mut().state = ComputerState::UNKNOWN;
+        instr!(self, LA, result, location);
+
+        self.cleanup_temp_var(result);
+        self.cleanup_temp_var(counter);
+        if let Some(slot) = base_slot {
+            self.cleanup_temp_var(slot);
+        }
+        Ok(())
+    }
+
     /// # Returns
     /// if the arguments were swapped
     fn put_ab(&mut self, left: &Expression, right: &Expression, is_commutative: bool) -> Res<bool> {
@@ -544,7 +1736,7 @@ impl Compiler {
                         instr!(
                             self,
                             LB,
-                            self.get_var(&ident.symbol, Range::default()).unwrap(),
+                            self.get_var(&ident.symbol, ident.location)?,
                             right.location
                         );
                     } else {
@@ -564,7 +1756,7 @@ impl Compiler {
                     instr!(
                         self,
                         LB,
-                        self.get_var(&ident.symbol, Range::default()).unwrap(),
+                        self.get_var(&ident.symbol, ident.location)?,
                         right.location
                     );
                 } else {
@@ -579,18 +1771,93 @@ impl Compiler {
         Ok(swapped)
     }
 
-    fn eval_assignment(&mut self, symbol: &str, value: &Expression) -> Res {
-        self.eval_expr(value)?;
+    /// like [`Self::eval_expr`], but a subexpression that appears more than
+    /// once is computed once and kept in a temp
+    fn eval_expr_reusing(&mut self, expr: &Expression) -> Res {
+        if !self.options.runs(Pass::CommonSubexpressions) || !is_pure(expr) {
+            return self.eval_expr(expr);
+        }
+        let Some(common) = self.smallest_repeated(expr) else {
+            return self.eval_expr(expr);
+        };
+        let location = common.location;
+        self.eval_expr(&common)?;
+        let slot = self.insert_temp_var(location)?;
+        instr!(self, SVA, slot, location);
+        // `$` can't appear in source, so this can't shadow a variable
+        let name = format!("${slot}");
+        self.last_scope_mut().variables.insert(name.clone(), slot);
+        let result = self.eval_expr_reusing(&replace_expr(expr, &common, &name));
+        self.last_scope_mut().variables.remove(&name);
+        self.cleanup_temp_var(slot);
+        result
+    }
+
+    /// the smallest non-constant binary expression that appears more than
+    /// once in `expr`, bigger ones that repeat are found once it is replaced
+    fn smallest_repeated(&mut self, expr: &Expression) -> Option<Expression> {
+        let mut candidates = vec![];
+        collect_binary_exprs(expr, &mut candidates);
+        candidates.sort_by_key(|candidate| expr_size(candidate));
+        candidates
+            .into_iter()
+            .filter(|candidate| count_matches(expr, candidate) > 1)
+            .find(|candidate| self.try_get_constant(candidate).is_none())
+            .cloned()
+    }
+
+    /// errors if `ident` names the index of a surrounding `repeat`
+    fn check_not_index(&self, ident: &Ident) -> Res {
+        for scope in self.visible_scopes() {
+            if scope.loop_index.as_ref() == Some(&ident.symbol) {
+                return err!(
+                    ErrorType::AssignedIndex(ident.symbol.clone()),
+                    ident.location
+                );
+            }
+            if scope.variables.contains_key(&ident.symbol)
+                || scope.inline_variables.contains_key(&ident.symbol)
+            {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn eval_assignment(&mut self, ident: &Ident, value: &Expression) -> Res {
+        self.check_not_index(ident)?;
+        if let ExpressionType::ArrayLiteral(elements) = &value.typ {
+            if self.get_array(ident).is_ok() {
+                return self.assign_array(ident, elements);
+            }
+        }
+        self.eval_expr_reusing(value)?;
 
-        let slot = self.insert_var(symbol, value.location)?;
+        let slot = self.insert_var(&ident.symbol, ident.location)?;
 
         instr!(self, SVA, slot, value.location);
 
         Ok(())
     }
 
+    /// writes the elements to the start of the array
+    fn assign_array(&mut self, ident: &Ident, elements: &[Expression]) -> Res {
+        for (index, element) in (0..).zip(elements) {
+            let address = self.array_address(
+                ident,
+                &Expression {
+                    typ: ExpressionType::NumericLiteral(index),
+                    location: element.location,
+                },
+            )?;
+            self.call_ram("write", &[element.clone(), address], element.location)?;
+        }
+        Ok(())
+    }
+
     fn eval_iassignment(&mut self, ident: &Ident, value: &Expression, operator: Operator) -> Res {
-        self.eval_expr(value)?;
+        self.check_not_index(ident)?;
+        self.eval_expr_reusing(value)?;
         self.put_into_b(&Expression {
             typ: ExpressionType::Identifier(ident.symbol.clone()),
             location: value.location,
@@ -604,12 +1871,35 @@ impl Compiler {
         Ok(())
     }
 
+    /// there is no instruction to store B, so this adds 1 in the ALU instead of
+    /// using `INB`
+    fn eval_increment(&mut self, ident: &Ident, operator: Operator, location: Range) -> Res {
+        self.check_not_index(ident)?;
+        let slot = self.get_var(&ident.symbol, ident.location)?;
+        let state = self.last_scope().state;
+        let variable = RegisterContents::Variable(slot);
+        if operator == Operator::Plus && state.b == variable && state.a != variable {
+            self.mark_read(&ident.symbol);
+            self.put_a_number(1, location);
+        } else {
+            self.put_into_a(&Expression {
+                typ: ExpressionType::Identifier(ident.symbol.clone()),
+                location: ident.location,
+            })?;
+            self.put_b_number(1, location);
+        }
+        self.put_op(operator, location);
+        self.save_to(slot, location);
+        Ok(())
+    }
+
     fn put_op(&mut self, operator: Operator, location: Range) {
         use Operator as O;
         match operator {
             O::Plus => instr!(self, ADD, location),
             O::Minus => instr!(self, SUB, location),
             O::Mult => instr!(self, MUL, location),
+            O::Pow => unreachable!("powers are built by eval_power"),
             O::And => instr!(self, AND, location),
             O::Or => instr!(self, OR, location),
             O::Xor => instr!(self, XOR, location),
@@ -626,10 +1916,23 @@ impl Compiler {
             ExpressionType::NumericLiteral(value) => Some(*value),
             ExpressionType::Identifier(symbol) => self.get_inline_var(symbol, value.location).ok(),
             ExpressionType::BinaryExpr { .. } => self.try_eval_const(value).ok(),
+            ExpressionType::Member { object, property } => self.module_constant(object, property),
             _ => None,
         }
     }
 
+    /// looks up a constant of a loaded module, like `colorscreen.red`
+    fn module_constant(&self, object: &Expression, property: &Ident) -> Option<i16> {
+        let ExpressionType::Identifier(symbol) = &object.typ else {
+            return None;
+        };
+        let module = self.aliases.get(symbol).unwrap_or(symbol);
+        if !self.modules.contains(module) {
+            return None;
+        }
+        constant(module, &property.symbol)
+    }
+
     /// puts a into b
     ///
     /// # Errors
@@ -659,6 +1962,7 @@ impl Compiler {
                     self.put_a_number(value, expr.location);
                 } else {
                     let var = self.get_var(symbol, expr.location)?;
+                    self.mark_read(symbol);
                     if let RegisterContents::Variable(v) = self.last_scope().state.a {
                         if v == var {
                             return Ok(());
@@ -705,6 +2009,7 @@ impl Compiler {
                     self.put_b_number(value, expr.location);
                 } else {
                     let var = self.get_var(symbol, expr.location)?;
+                    self.mark_read(symbol);
                     if let RegisterContents::Variable(v) = self.last_scope().state.b {
                         if v == var {
                             return Ok(());
@@ -725,6 +2030,14 @@ impl Compiler {
         Ok(())
     }
 
+    /// a write by the program itself, it can't use the slot `trace` writes to
+    pub fn write_out(&mut self, port: u8, location: Range) {
+        if port == TRACE_SLOT && !self.trace_slot_writes.contains(&location) {
+            self.trace_slot_writes.push(location);
+        }
+        self.save_to_out(port, location);
+    }
+
     #[inline]
     pub fn save_to_out(&mut self, port: u8, location: Range) {
         self.save_to(port + 32, location);
@@ -773,6 +2086,7 @@ impl Compiler {
         }
         let bytes = value.to_le_bytes();
         instr!(self, LAL, bytes[0], location);
+        // LAL zero-extends, so only a non-zero high byte needs loading
         if bytes[1] != 0 {
             instr!(self, LAH, bytes[1], location);
         }
@@ -796,32 +2110,32 @@ impl Compiler {
         match &function.typ {
             E::Member { object, property } => match &object.typ {
                 E::Identifier(symbol) => {
-                    module = symbol;
+                    module = self.aliases.get(symbol).unwrap_or(symbol).clone();
                     method = property;
                 }
-                _ => {
-                    return Err(Error {
-                        typ: Box::new(ErrorType::NonexistentModule(format!("{object:?}"))),
-                        location: function.location,
-                    })
-                }
+                _ => unreachable!("the parser only allows methods on identifiers"),
             },
-            _ => {
-                return Err(Error {
-                    typ: Box::new(ErrorType::UnknownMethod(format!("{function:?}"))),
-                    location: function.location,
-                })
+            // calls of user functions are statements, see `eval_statement`
+            E::Identifier(name) if self.functions.contains_key(name) => {
+                return err!(ErrorType::FunctionAsValue(name.clone()), function.location)
             }
+            E::Identifier(name) => {
+                return err!(
+                    ErrorType::NonexistentFunction(name.clone()),
+                    function.location
+                )
+            }
+            _ => unreachable!("the parser only allows calling identifiers and members"),
         }
-        if !self.modules.contains(module) {
+        if !self.modules.contains(&module) {
             return Err(Error {
-                typ: Box::new(ErrorType::UnlodadedModule(module.clone())),
+                typ: Box::new(ErrorType::UnlodadedModule(module)),
                 location: function.location,
             });
         }
 
         call(
-            module,
+            &module,
             self,
             &Call {
                 method_name: &method.symbol,
@@ -831,27 +2145,274 @@ impl Compiler {
         )
     }
 
-    fn replace_jump_marks(instructions: &mut [Instruction], jump_marks: &HashMap<u8, u8>) {
+    fn define_function(&mut self, ident: Ident, params: Vec<Ident>, body: Vec<Expression>) -> Res {
+        if self.functions.contains_key(&ident.symbol) {
+            return err!(
+                ErrorType::FunctionDefinedTwice(ident.symbol),
+                ident.location
+            );
+        }
+        self.functions
+            .insert(ident.symbol, Function { params, body });
+        Ok(())
+    }
+
+    /// inlines the body, with the arguments in fresh slots named after the params
+    fn eval_function_call(&mut self, name: &str, args: &[Expression], location: Range) -> Res {
+        let Some(function) = self.functions.get(name).cloned() else {
+            return err!(ErrorType::NonexistentFunction(name.to_owned()), location);
+        };
+        if self.calls.iter().any(|call| call == name) {
+            return err!(ErrorType::Recursion(name.to_owned()), location);
+        }
+        if args.len() != function.params.len() {
+            return err!(
+                ErrorType::WrongArgCount {
+                    name: name.to_owned(),
+                    expected: function.params.len(),
+                    got: args.len(),
+                },
+                location
+            );
+        }
+
+        // all arguments are evaluated before any param name is visible
+        let mut slots = vec![];
+        for arg in args {
+            self.eval_expr(arg)?;
+            let slot = self.insert_temp_var(arg.location)?;
+            self.save_to(slot, arg.location);
+            slots.push(slot);
+        }
+
+        // the body only sees the globals and its params, not the caller's locals
+        let caller_scope = std::mem::replace(&mut self.call_scope, self.scopes.len());
+        self.scopes.push(Scope::with_state(self.last_scope().state));
+        for (param, slot) in function.params.into_iter().zip(slots) {
+            self.last_scope_mut().variables.insert(param.symbol, slot);
+        }
+        // loops around the call can't be left from inside the function
+        let loops = std::mem::take(&mut self.loops);
+        self.calls.push(name.to_owned());
+        self.warn_unreachable(&function.body);
+        let result = function
+            .body
+            .into_iter()
+            .try_for_each(|line| self.eval_statement(line));
+        self.calls.pop();
+        self.loops = loops;
+        self.call_scope = caller_scope;
+        let state = self.pop_scope();
+        result?;
+
+        self.last_scope_mut().state = state;
+        Ok(())
+    }
+
+    /// errors on a jump to the end of a program that fills the whole ROM
+    fn replace_jump_marks(
+        instructions: &mut [Instruction],
+        jump_marks: &HashMap<u8, usize>,
+    ) -> Res {
         for i in instructions.iter_mut() {
             if i.variant.is_jump() {
-                i.arg = Some(
-                    *jump_marks
-                        .get(&i.arg.expect("jump does not have arg"))
-                        .expect("Invalid jump mark"),
-                );
+                let address = *jump_marks
+                    .get(&i.arg.expect("jump does not have arg"))
+                    .expect("Invalid jump mark");
+                let Ok(address) = u8::try_from(address) else {
+                    return err!(ErrorType::ProgramTooLarge(address + 1), i.orig_location);
+                };
+                i.arg = Some(address);
             }
         }
+        Ok(())
+    }
+
+    /// points every jump at the lowest mark with its target address and
+    /// drops the marks no jump uses anymore
+    fn merge_jump_marks(instructions: &mut [Instruction], jump_marks: &mut HashMap<u8, usize>) {
+        let mut canonical: HashMap<usize, u8> = HashMap::new();
+        for (&mark, &address) in jump_marks.iter() {
+            canonical
+                .entry(address)
+                .and_modify(|lowest| *lowest = (*lowest).min(mark))
+                .or_insert(mark);
+        }
+        for instr in instructions.iter_mut().filter(|i| i.variant.is_jump()) {
+            let mark = instr.arg.expect("jump does not have arg");
+            let address = jump_marks.get(&mark).expect("Invalid jump mark");
+            instr.arg = Some(canonical[address]);
+        }
+        jump_marks.retain(|mark, address| canonical[address] == *mark);
     }
 
-    fn move_jump_marks(jump_marks: &mut HashMap<u8, u8>, from: u8, by: u8) {
+    /// moves marks after `from`, a mark on `from` itself stays so it lands
+    /// on the instruction inserted there
+    fn move_jump_marks(jump_marks: &mut HashMap<u8, usize>, from: usize, by: usize) {
         for (_, value) in jump_marks.iter_mut() {
-            if *value >= from {
+            if *value > from {
                 *value += by;
             }
         }
     }
 
-    fn insert_disc_jumps(instructions: &mut Vec<Instruction>, jump_marks: &mut HashMap<u8, u8>) {
+    /// only counts marks a jump actually uses
+    fn is_jump_target(
+        instructions: &[Instruction],
+        jump_marks: &HashMap<u8, usize>,
+        index: usize,
+    ) -> bool {
+        instructions
+            .iter()
+            .filter(|instr| instr.variant.is_jump())
+            .filter_map(|instr| instr.arg.and_then(|mark| jump_marks.get(&mark)))
+            .any(|target| *target == index)
+    }
+
+    fn remove_instruction(
+        instructions: &mut Vec<Instruction>,
+        jump_marks: &mut HashMap<u8, usize>,
+        index: usize,
+    ) {
+        instructions.remove(index);
+        for mark in jump_marks.values_mut() {
+            if *mark > index {
+                *mark -= 1;
+            }
+        }
+    }
+
+    /// removes instructions after an unconditional jump that no jump lands on
+    fn remove_unreachable(
+        instructions: &mut Vec<Instruction>,
+        jump_marks: &mut HashMap<u8, usize>,
+    ) {
+        let mut reachable = true;
+        let mut i = 0;
+        while i < instructions.len() {
+            reachable |= Self::is_jump_target(instructions, jump_marks, i);
+            if !reachable {
+                Self::remove_instruction(instructions, jump_marks, i);
+                continue;
+            }
+            if matches!(
+                instructions[i].variant,
+                InstructionVariant::JMP | InstructionVariant::JMD
+            ) {
+                reachable = false;
+            }
+            i += 1;
+        }
+    }
+
+    /// removes unconditional jumps that land on the instruction right after them
+    fn remove_jumps_to_next(
+        instructions: &mut Vec<Instruction>,
+        jump_marks: &mut HashMap<u8, usize>,
+    ) {
+        let mut i = 0;
+        while i < instructions.len() {
+            let instr = &instructions[i];
+            if instr.variant == InstructionVariant::JMP
+                && instr
+                    .arg
+                    .and_then(|mark| jump_marks.get(&mark))
+                    .is_some_and(|target| *target == i + 1)
+            {
+                Self::remove_instruction(instructions, jump_marks, i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    /// removes loads of a slot or number the register already holds on every
+    /// path leading there, has to run while jumps still point to jump marks
+    fn remove_redundant_loads(
+        instructions: &mut Vec<Instruction>,
+        jump_marks: &mut HashMap<u8, usize>,
+    ) {
+        let states = Self::entry_states(instructions, jump_marks);
+        // backwards, so removing doesn't move the instructions still to check
+        for i in (0..instructions.len()).rev() {
+            let Some(state) = states[i] else {
+                continue;
+            };
+            let instr = &instructions[i];
+            let (register, high) = match instr.variant {
+                InstructionVariant::LA | InstructionVariant::LAL => {
+                    (state.a, InstructionVariant::LAH)
+                }
+                InstructionVariant::LB | InstructionVariant::LBL => {
+                    (state.b, InstructionVariant::LBH)
+                }
+                _ => continue,
+            };
+            let Some(arg) = instr.arg else {
+                continue;
+            };
+            // the high byte belongs to the load unless something jumps to it
+            let high_byte = instructions
+                .get(i + 1)
+                .filter(|next| next.variant == high)
+                .filter(|_| !Self::is_jump_target(instructions, jump_marks, i + 1))
+                .and_then(|next| next.arg);
+            let loaded = if matches!(
+                instr.variant,
+                InstructionVariant::LA | InstructionVariant::LB
+            ) {
+                // slots past the variables are io registers and can change at any time
+                if arg as usize >= VAR_SLOTS {
+                    continue;
+                }
+                RegisterContents::Variable(arg)
+            } else {
+                RegisterContents::Number(i16::from(arg) + (i16::from(high_byte.unwrap_or(0)) << 8))
+            };
+            if register != loaded {
+                continue;
+            }
+            if high_byte.is_some() {
+                Self::remove_instruction(instructions, jump_marks, i + 1);
+            }
+            Self::remove_instruction(instructions, jump_marks, i);
+        }
+    }
+
+    /// # Returns
+    /// the register contents every path into an instruction agrees on,
+    /// `None` for instructions no path reaches
+    fn entry_states(
+        instructions: &[Instruction],
+        jump_marks: &HashMap<u8, usize>,
+    ) -> Vec<Option<ComputerState>> {
+        let mut states: Vec<Option<ComputerState>> = vec![None; instructions.len()];
+        let mut pending = vec![(0, ComputerState::default())];
+        while let Some((i, incoming)) = pending.pop() {
+            let Some(instr) = instructions.get(i) else {
+                continue;
+            };
+            let state = states[i].map_or(incoming, |known| known.merge(incoming));
+            if states[i] == Some(state) {
+                continue;
+            }
+            states[i] = Some(state);
+
+            let mut after = state;
+            instr.execute(&mut after);
+            if instr.variant.is_jump() {
+                if let Some(target) = instr.arg.and_then(|mark| jump_marks.get(&mark)) {
+                    pending.push((*target, after));
+                }
+            }
+            if instr.variant != InstructionVariant::JMP {
+                pending.push((i + 1, after));
+            }
+        }
+        states
+    }
+
+    fn insert_disc_jumps(instructions: &mut Vec<Instruction>, jump_marks: &mut HashMap<u8, usize>) {
         loop {
             let mut changes = false;
 
@@ -863,15 +2424,19 @@ impl Compiler {
                 let location = instr.orig_location;
                 if instr.variant.is_jump() && !instr.variant.disc_jump() {
                     let mark = instr.arg.expect("Jump instruction doesn't have arg");
-                    let current_page = i / 64;
-                    let jump_page = jump_marks.get(&mark).expect("Invalid jump mark") / 64;
-                    if current_page != jump_page as usize {
+                    let current_page = i / PAGE_SIZE;
+                    let jump_page = *jump_marks.get(&mark).expect("Invalid jump mark") / PAGE_SIZE;
+                    if current_page != jump_page {
                         instr.variant = instr.variant.to_disc_jump();
                         instructions.insert(
                             i,
-                            Instruction::new(InstructionVariant::LCL, Some(jump_page), location),
+                            Instruction::new(
+                                InstructionVariant::LCL,
+                                Some(jump_page as u8),
+                                location,
+                            ),
                         );
-                        Self::move_jump_marks(jump_marks, i as u8, 1);
+                        Self::move_jump_marks(jump_marks, i, 1);
                         i += 1;
                         changes = true;
                     }
@@ -886,6 +2451,185 @@ impl Compiler {
     }
 }
 
+/// only reads variables, so evaluating a part of it twice gives the same value
+fn is_pure(expr: &Expression) -> bool {
+    match &expr.typ {
+        ExpressionType::Identifier(..) | ExpressionType::NumericLiteral(..) => true,
+        ExpressionType::BinaryExpr { left, right, .. } => is_pure(left) && is_pure(right),
+        _ => false,
+    }
+}
+
+/// compares the structure of pure expressions, ignoring their locations
+fn same_expr(a: &Expression, b: &Expression) -> bool {
+    match (&a.typ, &b.typ) {
+        (ExpressionType::Identifier(a), ExpressionType::Identifier(b)) => a == b,
+        (ExpressionType::NumericLiteral(a), ExpressionType::NumericLiteral(b)) => a == b,
+        (
+            ExpressionType::BinaryExpr {
+                left: a_left,
+                right: a_right,
+                operator: a_operator,
+            },
+            ExpressionType::BinaryExpr {
+                left: b_left,
+                right: b_right,
+                operator: b_operator,
+            },
+        ) => {
+            a_operator == b_operator
+                && ((same_expr(a_left, b_left) && same_expr(a_right, b_right))
+                    || (a_operator.is_commutative()
+                        && same_expr(a_left, b_right)
+                        && same_expr(a_right, b_left)))
+        }
+        _ => false,
+    }
+}
+
+fn collect_binary_exprs<'a>(expr: &'a Expression, into: &mut Vec<&'a Expression>) {
+    if let ExpressionType::BinaryExpr { left, right, .. } = &expr.typ {
+        into.push(expr);
+        collect_binary_exprs(left, into);
+        collect_binary_exprs(right, into);
+    }
+}
+
+fn expr_size(expr: &Expression) -> usize {
+    match &expr.typ {
+        ExpressionType::BinaryExpr { left, right, .. } => 1 + expr_size(left) + expr_size(right),
+        _ => 1,
+    }
+}
+
+/// how often `pattern` appears in `expr`, without counting matches inside matches
+fn count_matches(expr: &Expression, pattern: &Expression) -> usize {
+    if same_expr(expr, pattern) {
+        return 1;
+    }
+    match &expr.typ {
+        ExpressionType::BinaryExpr { left, right, .. } => {
+            count_matches(left, pattern) + count_matches(right, pattern)
+        }
+        _ => 0,
+    }
+}
+
+/// replaces every appearance of `pattern` with the variable `name`
+fn replace_expr(expr: &Expression, pattern: &Expression, name: &str) -> Expression {
+    if same_expr(expr, pattern) {
+        return Expression {
+            typ: ExpressionType::Identifier(name.to_owned()),
+            location: expr.location,
+        };
+    }
+    match &expr.typ {
+        ExpressionType::BinaryExpr {
+            left,
+            right,
+            operator,
+        } => Expression {
+            typ: ExpressionType::BinaryExpr {
+                left: Box::new(replace_expr(left, pattern, name)),
+                right: Box::new(replace_expr(right, pattern, name)),
+                operator: *operator,
+            },
+            location: expr.location,
+        },
+        _ => expr.clone(),
+    }
+}
+
+/// `stop` and `forever` loops that no `break` leaves
+fn never_finishes(statement: &Expression) -> bool {
+    match &statement.typ {
+        ExpressionType::Stop => true,
+        ExpressionType::EndlessLoop { body } => !body.iter().any(breaks_out),
+        _ => false,
+    }
+}
+
+/// statements that never continue to the one after them
+fn jumps_away(statement: &Expression) -> bool {
+    never_finishes(statement)
+        || matches!(
+            statement.typ,
+            ExpressionType::Break | ExpressionType::Continue
+        )
+}
+
+/// the variables `expression` assigns, including in nested blocks
+fn collect_assigned<'a>(expression: &'a Expression, into: &mut Vec<&'a Ident>) {
+    use ExpressionType as E;
+    match &expression.typ {
+        E::Assignment { ident, value } | E::IAssignment { ident, value, .. } => {
+            into.push(ident);
+            collect_assigned(value, into);
+        }
+        E::Increment { ident, .. } => into.push(ident),
+        E::Conditional {
+            body,
+            paths,
+            alternate,
+            ..
+        } => {
+            let bodies = std::iter::once(body)
+                .chain(paths.iter().map(|(_, body)| body))
+                .chain(alternate);
+            collect_assigned_in(bodies, into);
+        }
+        E::Match {
+            arms, alternate, ..
+        } => collect_assigned_in(arms.iter().map(|(_, body)| body).chain(alternate), into),
+        E::EndlessLoop { body }
+        | E::WhileLoop { body, .. }
+        | E::DoWhile { body, .. }
+        | E::Repeat { body, .. }
+        | E::Call { args: body, .. } => collect_assigned_in([body], into),
+        E::BinaryExpr { left, right, .. }
+        | E::EqExpr { left, right, .. }
+        | E::LogicalExpr { left, right, .. } => {
+            collect_assigned(left, into);
+            collect_assigned(right, into);
+        }
+        _ => {}
+    }
+}
+
+fn collect_assigned_in<'a>(
+    bodies: impl IntoIterator<Item = &'a Vec<Expression>>,
+    into: &mut Vec<&'a Ident>,
+) {
+    for expression in bodies.into_iter().flatten() {
+        collect_assigned(expression, into);
+    }
+}
+
+/// whether a `break` in `statement` leaves the loop it is in
+fn breaks_out(statement: &Expression) -> bool {
+    match &statement.typ {
+        ExpressionType::Break => true,
+        ExpressionType::Conditional {
+            body,
+            paths,
+            alternate,
+            ..
+        } => body
+            .iter()
+            .chain(paths.iter().flat_map(|(_, body)| body))
+            .chain(alternate.iter().flatten())
+            .any(breaks_out),
+        ExpressionType::Match {
+            arms, alternate, ..
+        } => arms
+            .iter()
+            .flat_map(|(_, body)| body)
+            .chain(alternate.iter().flatten())
+            .any(breaks_out),
+        _ => false,
+    }
+}
+
 fn eval_condition(
     condition: Expression,
 ) -> Res<(Box<Expression>, Box<Expression>, EqualityOperator)> {
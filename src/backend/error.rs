@@ -9,14 +9,48 @@ pub enum Type {
     NonexistentModule(String),
     UnlodadedModule(String),
     UnknownMethod(String),
+    UnknownModuleMethod {
+        module: String,
+        method: String,
+        available: &'static [&'static str],
+    },
     InvalidArgs(String),
+    WrongArgCount {
+        name: String,
+        expected: usize,
+        got: usize,
+    },
     CompileTimeArg(String),
     SomethingElseWentWrong(String),
     ModuleInitTwice(String),
-    EqInNormalExpr,
     NormalInEqExpr,
     UseOutsideGlobalScope,
     NoConstants,
+    InvalidAssembly(String),
+    InvalidPragma(String),
+    InvalidBinary(String),
+    ProgramTooLarge(usize),
+    TooManyJumpMarks(usize),
+    OutsideLoop(String),
+    NonexistentFunction(String),
+    FunctionDefinedTwice(String),
+    Recursion(String),
+    FunctionAsValue(String),
+    OffScreen(i16),
+    AssertionFailed,
+    InlineOverflow,
+    NonexistentArray(String),
+    InvalidArraySize(i16),
+    OutOfRam(i16),
+    IndexOutOfBounds(i16, i16),
+    MisplacedArrayLiteral,
+    AliasIsModule(String),
+    AliasTaken {
+        alias: String,
+        module: String,
+    },
+    TraceSlotWritten(u8),
+    AssignedIndex(String),
 }
 
 impl ErrorType for Type {
@@ -41,24 +75,83 @@ impl ErrorType for Type {
             Self::UnknownMethod(name) => {
                 format!("The method {name} doesn't exist")
             }
+            Self::UnknownModuleMethod {
+                module,
+                method,
+                available,
+            } => {
+                format!(
+                    "The method {method} doesn't exist on {module}, available: {}",
+                    available.join(", ")
+                )
+            }
             Self::InvalidArgs(args) => {
                 format!("The arguments {args} are invalid")
             }
+            Self::WrongArgCount {
+                name,
+                expected,
+                got,
+            } => {
+                let plural = if *expected == 1 { "" } else { "s" };
+                format!("{name} takes {expected} argument{plural}, but got {got}")
+            }
             Self::SomethingElseWentWrong(e) => {
                 format!("Something else has gone wrong: {e}. Please report this to the developer")
             }
             Self::ModuleInitTwice(name) => {
                 format!("The module {name} was initialilzed twice")
             }
-            Self::EqInNormalExpr => {
-                "You can't use an Equality Expression in a Normal Expression".to_string()
-            }
             Self::NormalInEqExpr => "You can't use a normal Expression here".to_string(),
             Self::UseOutsideGlobalScope => "You can only use 'use' in the global scope".to_string(),
             Self::CompileTimeArg(name) => {
                 format!("{name} has to be known at compile-time")
             }
-            Self::NoConstants => "Constants are only supported inside module calls".to_string(),
+            Self::NoConstants => "This constant doesn't exist or its module isn't used".to_string(),
+            Self::InvalidAssembly(reason) => format!("Invalid assembly: {reason}"),
+            Self::InvalidPragma(reason) => format!("Invalid pragma: {reason}"),
+            Self::InvalidBinary(reason) => format!("Invalid binary: {reason}"),
+            Self::NonexistentFunction(name) => format!("The function {name} doesn't exist"),
+            Self::FunctionDefinedTwice(name) => format!("The function {name} was defined twice"),
+            Self::Recursion(name) => format!("The function {name} can't call itself"),
+            Self::FunctionAsValue(name) => {
+                format!("The function {name} doesn't return anything, it can't be used as a value")
+            }
+            Self::OffScreen(value) => {
+                format!("The coordinate {value} is off the screen, it has to be from 0 to 63")
+            }
+            Self::NonexistentArray(name) => format!("The array {name} doesn't exist"),
+            Self::InvalidArraySize(size) => format!("An array can't have {size} elements"),
+            Self::OutOfRam(size) => format!("There is no room left in RAM for {size} elements"),
+            Self::IndexOutOfBounds(index, size) => {
+                format!("The index {index} is out of bounds for an array of {size} elements")
+            }
+            Self::TraceSlotWritten(slot) => {
+                format!("Out slot {slot} is where trace writes its tags, it can't be written to in a program that traces")
+            }
+            Self::AssignedIndex(name) => {
+                format!("{name} is the index of the loop, it can't be assigned to")
+            }
+            Self::AliasIsModule(name) => {
+                format!("{name} is already a module, it can't be an alias")
+            }
+            Self::AliasTaken { alias, module } => {
+                format!("{alias} is already an alias of {module}")
+            }
+            Self::MisplacedArrayLiteral => {
+                "An array literal can only be assigned to an array or added to a list".to_string()
+            }
+            Self::InlineOverflow => {
+                "The result of this expression doesn't fit in 16 bits".to_string()
+            }
+            Self::AssertionFailed => "The assertion doesn't hold".to_string(),
+            Self::OutsideLoop(keyword) => format!("'{keyword}' can only be used inside a loop"),
+            Self::ProgramTooLarge(count) => {
+                format!("The program is too large, {count} instructions don't fit into the ROM")
+            }
+            Self::TooManyJumpMarks(count) => {
+                format!("The program needs {count} jump marks, only 256 fit")
+            }
         }
     }
 }
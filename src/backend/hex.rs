@@ -0,0 +1,39 @@
+use std::fmt::Write;
+
+use super::Instruction;
+
+/// data bytes per record
+const RECORD_LEN: usize = 16;
+const DATA: u8 = 0x00;
+const END_OF_FILE: u8 = 0x01;
+
+/// Encodes the instructions as Intel HEX, each word big-endian at
+/// byte address `2 * index`
+#[must_use]
+pub fn to_intel_hex(instructions: &[Instruction]) -> String {
+    let bytes: Vec<u8> = instructions
+        .iter()
+        .flat_map(|instr| instr.to_bin().to_be_bytes())
+        .collect();
+    let mut hex = String::new();
+    for (i, chunk) in bytes.chunks(RECORD_LEN).enumerate() {
+        push_record(&mut hex, (i * RECORD_LEN) as u16, DATA, chunk);
+    }
+    push_record(&mut hex, 0, END_OF_FILE, &[]);
+    hex
+}
+
+fn push_record(hex: &mut String, address: u16, typ: u8, data: &[u8]) {
+    let [high, low] = address.to_be_bytes();
+    let header = [data.len() as u8, high, low, typ];
+    let checksum = header
+        .iter()
+        .chain(data)
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+        .wrapping_neg();
+    hex.push(':');
+    for byte in header.iter().chain(data).chain([&checksum]) {
+        let _ = write!(hex, "{byte:02X}");
+    }
+    hex.push('\n');
+}
@@ -1,20 +1,38 @@
 use core::panic;
-use std::fmt::{self, Debug};
+use std::{
+    fmt::{self, Debug},
+    str::FromStr,
+};
 
 use table_enum::table_enum;
 
-table_enum! {
-    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-    #[allow(unused)]
-    pub enum InstructionVariant(
-    name: &'static str,
-    disc_jump: bool,
-    jump: bool,
-    id: u8,
-    instant: bool,
-    alu: bool,
-    has_arg: bool,
-) {
+/// declares [`InstructionVariant`] from its table and lists every variant in
+/// [`InstructionVariant::ALL`], so the list can't miss one
+macro_rules! instruction_variants {
+    ($($variant:ident($($field:tt),*),)*) => {
+        table_enum! {
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            #[allow(unused)]
+            pub enum InstructionVariant(
+            name: &'static str,
+            disc_jump: bool,
+            jump: bool,
+            id: u8,
+            instant: bool,
+            alu: bool,
+            has_arg: bool,
+        ) {
+            $($variant($($field),*),)*
+        }}
+
+        impl InstructionVariant {
+            /// every variant, in declaration order
+            pub const ALL: [Self; [$(stringify!($variant)),*].len()] = [$(Self::$variant),*];
+        }
+    };
+}
+
+instruction_variants! {
     STOP("STP", false, false, 0, false, false, false),
 
     NON("NON", false, false, 0, true, false, false),
@@ -77,10 +95,27 @@ table_enum! {
     SDGE("SDGE", true, true, 12, true, true, true),
     SDL("SDL", true, true, 13, true, true, true),
     SDLE("SDLE", true, true, 14, true, true, true),
-
-}}
+}
 
 impl InstructionVariant {
+    /// looks up a variant by its mnemonic
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|variant| variant.name() == name)
+    }
+
+    /// Inverse of [`InstructionVariant::to_byte`].
+    ///
+    /// Some variants share the same byte: `RW`/`RR`, `RC`/`INB` and each
+    /// jump with its `S*` counterpart (`JMP`/`SMP`, `JE`/`SE`, ...).
+    /// The one declared first wins, so those decode to `RW`, `RC` and the jumps
+    #[must_use]
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|variant| variant.to_byte() == byte)
+    }
+
     /// Converts a normal jump into a disc jump
     ///
     /// # Panics
@@ -103,6 +138,22 @@ impl InstructionVariant {
         }
     }
 
+    /// Worst-case redstone ticks this instruction takes to execute.
+    ///
+    /// Instant instructions finish in a single tick. The rest are estimates:
+    /// `INB` (2) and `RC` (3) go through the address register, the shifts
+    /// (`SUP`/`SDN`, 4) and `MUL` (8) run through the ALU more than once
+    #[must_use]
+    pub const fn cycles(self) -> u8 {
+        match self {
+            Self::INB => 2,
+            Self::RC => 3,
+            Self::SUP | Self::SDN => 4,
+            Self::MUL => 8,
+            _ => 1,
+        }
+    }
+
     #[must_use]
     pub const fn is_jump(self) -> bool {
         self.jump()
@@ -130,13 +181,21 @@ impl InstructionVariant {
     }
 }
 
-#[derive(PartialEq, Eq)]
 pub struct Instruction {
     pub variant: InstructionVariant,
     pub arg: Option<u8>,
     pub orig_location: Range,
 }
 
+/// Ignores `orig_location`, use [`Instruction::eq_with_location`] to compare it too
+impl PartialEq for Instruction {
+    fn eq(&self, other: &Self) -> bool {
+        self.variant == other.variant && self.arg == other.arg
+    }
+}
+
+impl Eq for Instruction {}
+
 impl fmt::Display for Instruction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.to_string(f)
@@ -149,9 +208,122 @@ impl Debug for Instruction {
     }
 }
 
-use crate::frontend::{EqualityOperator, Range};
+use crate::{
+    err,
+    error::Error,
+    frontend::{EqualityOperator, Location, Range},
+};
 
-use super::{ComputerState, RamPage, RegisterContents};
+use super::{ComputerState, ErrorType, RamPage, RegisterContents};
+
+/// Parses assembly as written to `.asm` files, one instruction per line.
+/// Empty lines and anything after a `;` are ignored
+///
+/// # Errors
+///
+/// on an unknown mnemonic or a missing, superfluous or invalid argument
+pub fn parse_asm(asm: &str) -> Result<Vec<Instruction>, Error> {
+    asm.lines()
+        .enumerate()
+        .filter_map(|(number, line)| {
+            let line = line.split(';').next().unwrap_or_default();
+            if line.trim().is_empty() {
+                return None;
+            }
+            let location = Range(
+                Location(number as u16, 1),
+                Location(number as u16, line.trim_end().len() as u16),
+            );
+            Some(
+                line.parse::<Instruction>()
+                    .map(|instr| Instruction {
+                        orig_location: location,
+                        ..instr
+                    })
+                    .map_err(|err| Error { location, ..err }),
+            )
+        })
+        .collect()
+}
+
+/// Parses the `.bin` format, one 16-digit binary word per line.
+/// Empty lines are ignored
+///
+/// # Errors
+///
+/// on a line that isn't a 16-bit binary number or doesn't decode to an instruction
+pub fn parse_bin(bin: &str) -> Result<Vec<Instruction>, Error> {
+    bin.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            let line = line.trim();
+            let location = Range(
+                Location(number as u16, 1),
+                Location(number as u16, line.len() as u16),
+            );
+            if line.len() != 16 || !line.bytes().all(|bit| matches!(bit, b'0' | b'1')) {
+                return err!(
+                    ErrorType::InvalidBinary(format!("{line} is not a 16-bit binary word")),
+                    location
+                );
+            }
+            let word = line
+                .bytes()
+                .fold(0, |word, bit| word << 1 | u16::from(bit == b'1'));
+            let Some(instr) = Instruction::from_bin(word) else {
+                return err!(
+                    ErrorType::InvalidBinary(format!("{line} is not a valid instruction")),
+                    location
+                );
+            };
+            Ok(Instruction {
+                orig_location: location,
+                ..instr
+            })
+        })
+        .collect()
+}
+
+impl FromStr for Instruction {
+    type Err = Error;
+
+    /// parses a single line of assembly like `LA 3`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        let Some(mnemonic) = parts.next() else {
+            return err!(
+                ErrorType::InvalidAssembly("empty line".to_string()),
+                Range::default()
+            );
+        };
+        let Some(variant) = InstructionVariant::from_name(mnemonic) else {
+            return err!(
+                ErrorType::InvalidAssembly(format!("unknown mnemonic {mnemonic}")),
+                Range::default()
+            );
+        };
+        let arg = match parts.next() {
+            Some(arg) => Some(arg.parse::<u8>().map_err(|_| Error {
+                typ: Box::new(ErrorType::InvalidAssembly(format!(
+                    "{arg} is not a valid argument"
+                ))),
+                location: Range::default(),
+            })?),
+            None => None,
+        };
+        if parts.next().is_some() || arg.is_some() != variant.has_arg() {
+            return err!(
+                ErrorType::InvalidAssembly(format!(
+                    "{mnemonic} takes {} argument",
+                    if variant.has_arg() { "one" } else { "no" }
+                )),
+                Range::default()
+            );
+        }
+        Ok(Self::new(variant, arg, Range::default()))
+    }
+}
 
 impl Instruction {
     /// Creates a new [`Instruction`].
@@ -169,11 +341,33 @@ impl Instruction {
         }
     }
 
+    #[must_use]
+    pub fn eq_with_location(&self, other: &Self) -> bool {
+        self == other && self.orig_location == other.orig_location
+    }
+
     #[must_use]
     pub fn to_bin(&self) -> u16 {
         (u16::from(self.arg.unwrap_or(0)) << 8) | u16::from(self.variant.to_byte())
     }
 
+    /// Decodes a word produced by [`Instruction::to_bin`], see
+    /// [`InstructionVariant::from_byte`] for how shared bytes are resolved
+    ///
+    /// Returns `None` for an unknown variant byte or an argument on a
+    /// variant that doesn't take one
+    #[must_use]
+    pub fn from_bin(word: u16) -> Option<Self> {
+        let [byte, arg] = word.to_le_bytes();
+        let variant = InstructionVariant::from_byte(byte)?;
+        let arg = match (variant.has_arg(), arg) {
+            (true, arg) => Some(arg),
+            (false, 0) => None,
+            (false, _) => return None,
+        };
+        Some(Self::new(variant, arg, Range::default()))
+    }
+
     /// Used by Debug and Display
     ///
     /// # Errors
@@ -190,13 +384,27 @@ impl Instruction {
         use InstructionVariant as IV;
         use RegisterContents as RC;
         match self.variant {
-            IV::LA | IV::SVA => on.a = RC::Variable(self.arg.unwrap_or(0)),
+            IV::LA => on.a = RC::Variable(self.arg.unwrap_or(0)),
+            IV::SVA => {
+                let slot = RC::Variable(self.arg.unwrap_or(0));
+                // the slot changed, so other registers holding it are stale
+                if on.b == slot {
+                    on.b = RC::Unknown;
+                }
+                if on.c == slot {
+                    on.c = RC::Unknown;
+                }
+                if on.ram_page == RamPage::OfVariable(self.arg.unwrap_or(0)) {
+                    on.ram_page = RamPage::Unknown;
+                }
+                on.a = slot;
+            }
             IV::LB => on.b = RC::Variable(self.arg.unwrap_or(0)),
             IV::LAL => on.a = RC::Number(self.arg.unwrap_or(0).into()),
             IV::LAH => {
                 on.a = match on.a {
                     RC::Number(value) => {
-                        RC::Number(value + (i16::from(self.arg.unwrap_or(0)) << 8))
+                        RC::Number(value.wrapping_add(i16::from(self.arg.unwrap_or(0)) << 8))
                     }
                     _ => RC::Unknown,
                 }
@@ -205,22 +413,33 @@ impl Instruction {
             IV::LBH => {
                 on.b = match on.b {
                     RC::Number(value) => {
-                        RC::Number(value + (i16::from(self.arg.unwrap_or(0)) << 8))
+                        RC::Number(value.wrapping_add(i16::from(self.arg.unwrap_or(0)) << 8))
+                    }
+                    _ => RC::Unknown,
+                }
+            }
+            IV::SUP | IV::SDN => {
+                on.a = match on.a {
+                    RC::Number(a) => {
+                        let by = self.arg.unwrap_or(0);
+                        RC::Number(if self.variant == IV::SUP {
+                            a.wrapping_shl(by.into())
+                        } else {
+                            a >> by
+                        })
                     }
                     _ => RC::Unknown,
                 }
             }
-            IV::ADD | IV::SUB | IV::MUL | IV::AND | IV::OR | IV::XOR | IV::SUP | IV::SDN => {
+            IV::ADD | IV::SUB | IV::MUL | IV::AND | IV::OR | IV::XOR => {
                 on.a = match (on.a, on.b) {
                     (RC::Number(a), RC::Number(b)) => RC::Number(match self.variant {
-                        IV::ADD => a + b,
-                        IV::SUB => a - b,
+                        IV::ADD => a.wrapping_add(b),
+                        IV::SUB => a.wrapping_sub(b),
                         IV::AND => a & b,
                         IV::OR => a | b,
                         IV::XOR => a ^ b,
-                        IV::SUP => a << b,
-                        IV::SDN => a >> b,
-                        IV::MUL => a * b,
+                        IV::MUL => a.wrapping_mul(b),
                         _ => unreachable!(),
                     }),
                     _ => RC::Unknown,
@@ -231,13 +450,14 @@ impl Instruction {
             IV::RR => on.a = RC::Unknown,
             IV::INB => {
                 on.b = match on.b {
-                    RC::Number(value) => RC::Number(value + 1),
+                    RC::Number(value) => RC::Number(value.wrapping_add(1)),
                     _ => RC::Unknown,
                 }
             }
             IV::RC => {
                 on.ram_page = match on.b {
                     RC::Number(address) => RamPage::ThisOne((address / 16).try_into().unwrap_or(0)),
+                    RC::Variable(slot) => RamPage::OfVariable(slot),
                     _ => RamPage::Unknown,
                 }
             }
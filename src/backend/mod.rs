@@ -1,13 +1,20 @@
 mod compiler;
 mod error;
+mod hex;
 pub mod instruction;
 #[macro_use]
 mod module;
+mod options;
 mod types;
 
-pub use compiler::compile_program;
-pub use instruction::{Instruction, InstructionVariant};
+pub use compiler::{
+    compile_program, compile_with_details, compile_with_options, compile_with_warnings,
+    Compilation, Symbols,
+};
+pub use hex::to_intel_hex;
+pub use instruction::{parse_asm, parse_bin, Instruction, InstructionVariant};
+pub use options::{Options, Pass};
 
 use compiler::Compiler;
 use error::Type as ErrorType;
-use types::{ComputerState, Instr, RamPage, RegisterContents, Scope};
+use types::{Array, ComputerState, Function, Instr, Loop, RamPage, RegisterContents, Scope};
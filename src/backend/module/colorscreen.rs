@@ -31,6 +31,8 @@ colorscreen.flip()
 colorscreen.fill(from, to, color)
 colorscreen.fill_xy(x1, y1, x2, y2, color)
 colorscreen.fill_screen(color)
+colorscreen.gradient(x1, y1, x2, y2)
+colorscreen.line(x1, y1, x2, y2, color)
 colorscreen.orange = 0x1000
 colorscreen.color_of(color_idx)
 */
@@ -42,23 +44,27 @@ const SCREENPOS2_REG: u8 = 6;
 const PAINT: i16 = 1;
 const FLIP: i16 = 2;
 
-use std::num::NonZeroI16;
+const COLORS: i16 = 16;
 
 use crate::{
     backend::compiler::Compiler,
     err,
-    frontend::{Expression, ExpressionType},
+    frontend::{Expression, ExpressionType, Range},
     instr, modul,
 };
 
-use super::{arg_parse, screen::put_xy, Arg, Call, ErrorType, Res};
+use super::{
+    arg_parse, place_merge,
+    screen::{check_coords, draw_line, edge_loop, put_xy},
+    Arg, Call, ErrorType, Operand, Res,
+};
 
-modul!(set set_at fill fill_xy fill_screen flip color_of);
+modul!(set set_at fill fill_xy fill_screen flip color_of gradient line);
 
 fn fill_screen(compiler: &mut Compiler, call: &Call) -> Res {
     let [color] = arg_parse(compiler, [Arg::Number("color")], call)?;
-    match is_const_color(color) {
-        Some(color) => compiler.put_a_number(color.into(), call.location),
+    match compiler.try_get_constant(color) {
+        Some(color) => compiler.put_a_number(color, call.location),
         None => compiler.eval_expr(color)?,
     }
     compiler.save_to_out(SCREENPOS1_REG, call.location);
@@ -92,10 +98,10 @@ fn put_xy_color(
     y: &Expression,
     call: &Call<'_>,
 ) -> Res {
-    match is_const_color(color) {
+    match compiler.try_get_constant(color) {
         Some(color) => {
             put_xy(compiler, x, y, call.location, 6)?;
-            compiler.put_b_number(color.into(), call.location);
+            compiler.put_b_number(color, call.location);
             instr!(compiler, OR, call.location);
         }
         None => {
@@ -185,13 +191,215 @@ fn fill_xy(compiler: &mut Compiler, call: &Call) -> Res {
     Ok(())
 }
 
+/// fills the rectangle with vertical bands cycling through all colors from
+/// left to right, the corners can be given in any order
+///
+/// Constant corners are unrolled into one `fill_xy` per band, otherwise
+/// every column is filled in a loop
+fn gradient(compiler: &mut Compiler, call: &Call) -> Res {
+    let coords = arg_parse(
+        compiler,
+        [
+            Arg::Number("x1"),
+            Arg::Number("y1"),
+            Arg::Number("x2"),
+            Arg::Number("y2"),
+        ],
+        call,
+    )?;
+    check_coords(compiler, &coords)?;
+    if let [Some(x1), Some(y1), Some(x2), Some(y2)] =
+        coords.map(|coord| compiler.try_get_constant(coord))
+    {
+        return constant_gradient(compiler, [x1.min(x2), y1, x1.max(x2), y2], call);
+    }
+
+    let location = call.location;
+    let [x1, y1, x2, y2] = coords;
+    let x1 = Operand::new(compiler, x1, location)?;
+    let y1 = Operand::new(compiler, y1, location)?;
+    let x2 = Operand::new(compiler, x2, location)?;
+    let y2 = Operand::new(compiler, y2, location)?;
+    let width = compiler.insert_temp_var(location)?;
+    let progress = compiler.insert_temp_var(location)?;
+    let color = compiler.insert_temp_var(location)?;
+    let column = compiler.insert_temp_var(location)?;
+
+    // width = |x2 - x1| + 1
+    let positive = compiler.insert_jump_mark();
+    x2.put_into_a(compiler, location)?;
+    x1.put_into_b(compiler, location)?;
+    instr!(compiler, SUB, location);
+    compiler.put_b_number(0, location);
+    instr!(compiler, JGE, positive, location);
+    compiler.save_to(width, location);
+    compiler.put_a_number(0, location);
+    instr!(compiler, LB, width, location);
+    instr!(compiler, SUB, location);
+    place_merge(compiler, positive);
+    compiler.put_b_number(1, location);
+    instr!(compiler, ADD, location);
+    compiler.save_to(width, location);
+    compiler.put_a_number(0, location);
+    compiler.save_to(progress, location);
+    compiler.save_to(color, location);
+
+    edge_loop(compiler, column, &x1, &x2, location, |compiler, column| {
+        column.put_into_a(compiler, location)?;
+        instr!(compiler, SUP, 6, location);
+        y1.put_into_b(compiler, location)?;
+        instr!(compiler, OR, location);
+        instr!(compiler, LB, color, location);
+        instr!(compiler, OR, location);
+        compiler.save_to_out(SCREENPOS1_REG, location);
+        column.put_into_a(compiler, location)?;
+        instr!(compiler, SUP, 6, location);
+        y2.put_into_b(compiler, location)?;
+        instr!(compiler, OR, location);
+        compiler.save_to_out(SCREENPOS2_REG, location);
+        compiler.put_a_number(PAINT, location);
+        compiler.save_to_out(SCREENOP_REG, location);
+        next_color(compiler, progress, color, width, location);
+        Ok(())
+    })?;
+
+    for slot in [width, progress, color, column] {
+        compiler.cleanup_temp_var(slot);
+    }
+    for corner in [x1, y1, x2, y2] {
+        corner.cleanup(compiler);
+    }
+    Ok(())
+}
+
+/// one `fill_xy` per band from `x1` to `x2`, `x1 <= x2`
+fn constant_gradient(compiler: &mut Compiler, [x1, y1, x2, y2]: [i16; 4], call: &Call) -> Res {
+    let literal = |value| Expression {
+        typ: ExpressionType::NumericLiteral(value),
+        location: call.location,
+    };
+    let width = x2 - x1 + 1;
+    let color_at = |x: i16| (x - x1) * COLORS / width;
+
+    let mut start = x1;
+    while start <= x2 {
+        let color = color_at(start);
+        let mut end = start;
+        while end < x2 && color_at(end + 1) == color {
+            end += 1;
+        }
+        put_xy_color(
+            compiler,
+            &literal(color << 12),
+            &literal(start),
+            &literal(y1),
+            call,
+        )?;
+        compiler.save_to_out(SCREENPOS1_REG, call.location);
+        put_xy(compiler, &literal(end), &literal(y2), call.location, 6)?;
+        compiler.save_to_out(SCREENPOS2_REG, call.location);
+        compiler.put_a_number(PAINT, call.location);
+        compiler.save_to_out(SCREENOP_REG, call.location);
+        start = end + 1;
+    }
+    Ok(())
+}
+
+/// Moves `color` on by the columns since the last band, without dividing.
+///
+/// `progress` is `16 * columns - width * colors` so far, every column adds
+/// 16 and each time it reaches `width` the next color starts
+fn next_color(compiler: &mut Compiler, progress: u8, color: u8, width: u8, location: Range) {
+    instr!(compiler, LA, progress, location);
+    compiler.put_b_number(COLORS, location);
+    instr!(compiler, ADD, location);
+    compiler.save_to(progress, location);
+    let carry = compiler.insert_jump_mark();
+    let done = compiler.insert_jump_mark();
+    place_merge(compiler, carry);
+    instr!(compiler, LA, progress, location);
+    instr!(compiler, LB, width, location);
+    instr!(compiler, JL, done, location);
+    instr!(compiler, SUB, location);
+    compiler.save_to(progress, location);
+    instr!(compiler, LA, color, location);
+    compiler.put_b_number(1 << 12, location);
+    instr!(compiler, ADD, location);
+    compiler.save_to(color, location);
+    instr!(compiler, JMP, carry, location);
+    place_merge(compiler, done);
+}
+
+fn line(compiler: &mut Compiler, call: &Call) -> Res {
+    let [x1, y1, x2, y2, color] = arg_parse(
+        compiler,
+        [
+            Arg::Number("x1"),
+            Arg::Number("y1"),
+            Arg::Number("x2"),
+            Arg::Number("y2"),
+            Arg::Number("color"),
+        ],
+        call,
+    )?;
+    check_coords(compiler, &[x1, y1, x2, y2])?;
+    let color = match compiler.try_get_constant(color) {
+        Some(color) => Operand::Constant(color),
+        None => Operand::new(compiler, color, call.location)?,
+    };
+    draw_line(
+        compiler,
+        [x1, y1, x2, y2],
+        call.location,
+        |compiler, x, y| paint_pixel(compiler, x, y, &color, call.location),
+    )?;
+    color.cleanup(compiler);
+    Ok(())
+}
+
+/// paints the pixel at `(x, y)`, keeping the color in the upper nibble
+fn paint_pixel(
+    compiler: &mut Compiler,
+    x: &Operand,
+    y: &Operand,
+    color: &Operand,
+    location: Range,
+) -> Res {
+    match (x, y, color) {
+        (Operand::Constant(x), Operand::Constant(y), Operand::Constant(color)) => {
+            compiler.put_a_number(x << 6 | y | color, location);
+        }
+        (Operand::Constant(x), Operand::Constant(y), _) => {
+            compiler.put_a_number(x << 6 | y, location);
+            color.put_into_b(compiler, location)?;
+            instr!(compiler, OR, location);
+        }
+        _ => {
+            x.put_into_a(compiler, location)?;
+            instr!(compiler, SUP, 6, location);
+            y.put_into_b(compiler, location)?;
+            instr!(compiler, OR, location);
+            color.put_into_b(compiler, location)?;
+            instr!(compiler, OR, location);
+        }
+    }
+    compiler.save_to_out(SCREENPOS1_REG, location);
+    compiler.save_to_out(SCREENPOS2_REG, location);
+    compiler.put_a_number(PAINT, location);
+    compiler.save_to_out(SCREENOP_REG, location);
+    Ok(())
+}
+
 fn load_position_color(
     compiler: &mut Compiler,
     position: &Expression,
     color: &Expression,
     call: &Call,
 ) -> Res {
-    match (compiler.try_get_constant(position), is_const_color(color)) {
+    match (
+        compiler.try_get_constant(position),
+        compiler.try_get_constant(color),
+    ) {
         (None, None) => {
             let temp = compiler.insert_temp_var(call.location)?;
             compiler.eval_expr(color)?;
@@ -203,7 +411,7 @@ fn load_position_color(
         }
         (None, Some(color)) => {
             compiler.eval_expr(position)?;
-            compiler.put_b_number(color.into(), call.location);
+            compiler.put_b_number(color, call.location);
             instr!(compiler, OR, call.location);
         }
         (Some(pos), None) => {
@@ -211,7 +419,7 @@ fn load_position_color(
             compiler.put_b_number(pos, call.location);
             instr!(compiler, OR, call.location);
         }
-        (Some(pos), Some(color)) => compiler.put_a_number(pos | i16::from(color), call.location),
+        (Some(pos), Some(color)) => compiler.put_a_number(pos | color, call.location),
     }
     Ok(())
 }
@@ -226,8 +434,9 @@ fn color_of(compiler: &mut Compiler, call: &Call) -> Res {
     Ok(())
 }
 
-fn get_color(color: &str) -> Option<NonZeroI16> {
-    NonZeroI16::new(
+/// the colors as `colorscreen.red`, already shifted into the upper nibble
+pub fn constant(color: &str) -> Option<i16> {
+    Some(
         match color {
             "white" => 0,
             "orange" => 1,
@@ -250,21 +459,6 @@ fn get_color(color: &str) -> Option<NonZeroI16> {
     )
 }
 
-fn is_const_color(expr: &Expression) -> Option<NonZeroI16> {
-    match &expr.typ {
-        ExpressionType::Member { object, property } => {
-            let color = get_color(&property.symbol);
-            match color {
-                Some(_) if matches!(&object.typ, ExpressionType::Identifier(name) if name == "colorscreen") => {
-                    color
-                }
-                _ => None,
-            }
-        }
-        _ => None,
-    }
-}
-
 fn is_color_of_call(expr: &ExpressionType) -> bool {
     match expr {
         ExpressionType::Call { args, function } => match &function.typ {
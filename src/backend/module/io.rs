@@ -4,56 +4,83 @@ use crate::{
         Compiler,
     },
     err,
-    error::Error,
+    frontend::{Expression, ExpressionType, Range},
     instr, modul,
 };
 
-use super::{Call, ErrorType, Res};
+use super::{place_merge, Call, ErrorType, Res};
 
 modul!(read write);
 
 fn read(compiler: &mut Compiler, call: &Call) -> Res {
-    let args = arg_parse(compiler, [Arg::Constant("Inslot")], call)?;
-
-    let slot = compiler.try_get_constant(args[0]).unwrap();
-    if !(0..8).contains(&slot) {
-        return Err(Error {
-            typ: Box::new(ErrorType::InvalidArgs(
-                "Input slot has to be from 0 to 7".to_string(),
-            )),
-            location: call.args.first().unwrap().location,
-        });
+    let [slot] = arg_parse(compiler, [Arg::Number("Inslot")], call)?;
+    if compiler.try_get_constant(slot).is_none() {
+        return read_runtime(compiler, slot, call.location);
     }
-
-    let slot: u8 = slot.try_into().unwrap_or(0);
+    let slot = get_slot(compiler, slot, "Inslot", "Input")?;
 
     instr!(compiler, LA, slot + 32, call.location);
 
     Ok(())
 }
 
+/// There are no indirect loads, so a slot only known at runtime is compared
+/// against every slot. Anything other than 0 to 6 reads slot 7
+fn read_runtime(compiler: &mut Compiler, slot: &Expression, location: Range) -> Res {
+    if Compiler::can_put_into_b(slot) {
+        compiler.put_into_b(slot)?;
+    } else {
+        compiler.eval_expr(slot)?;
+        compiler.switch(location)?;
+    }
+    let end = compiler.insert_jump_mark();
+    for number in 0..7 {
+        let next = compiler.insert_jump_mark();
+        compiler.put_a_number(number, location);
+        instr!(compiler, JNE, next, location);
+        instr!(compiler, LA, number as u8 + 32, location);
+        instr!(compiler, JMP, end, location);
+        place_merge(compiler, next);
+    }
+    instr!(compiler, LA, 39, location);
+    place_merge(compiler, end);
+    Ok(())
+}
+
 fn write(compiler: &mut Compiler, call: &Call) -> Res {
-    let args = arg_parse(
+    let [value, slot] = arg_parse(
         compiler,
-        [Arg::Number("value"), Arg::Constant("Outslot")],
+        [Arg::Number("value"), Arg::Number("Outslot")],
         call,
     )?;
+    let slot = get_slot(compiler, slot, "Outslot", "Output")?;
 
-    let slot = compiler.try_get_constant(args[1]).unwrap();
-    if !(0..8).contains(&slot) {
-        return Err(Error {
-            typ: Box::new(ErrorType::InvalidArgs(
-                "Output slot has to be from 0 to 7".to_string(),
-            )),
-            location: call.args.get(1).unwrap().location,
-        });
-    }
+    compiler.eval_expr(value)?;
 
-    let slot: u8 = slot.try_into().unwrap_or(0);
+    compiler.write_out(slot, call.location);
 
-    compiler.eval_expr(&call.args[0])?;
+    Ok(())
+}
 
-    instr!(compiler, SVA, slot + 32, call.location);
+/// a slot given as a constant like `io.slot3`
+fn get_slot(compiler: &mut Compiler, slot: &Expression, name: &str, kind: &str) -> Res<u8> {
+    let Some(number) = compiler.try_get_constant(slot) else {
+        if matches!(slot.typ, ExpressionType::Member { .. }) {
+            return err!(ErrorType::NoConstants, slot.location);
+        }
+        return err!(ErrorType::CompileTimeArg(name.to_string()), slot.location);
+    };
+    if !(0..8).contains(&number) {
+        return err!(
+            ErrorType::InvalidArgs(format!("{kind} slot has to be from 0 to 7")),
+            slot.location
+        );
+    }
+    Ok(number.try_into().unwrap_or(0))
+}
 
-    Ok(())
+/// `slot0` to `slot7`, the number of the slot
+pub fn constant(property: &str) -> Option<i16> {
+    let number = property.strip_prefix("slot")?.parse().ok()?;
+    (0..8).contains(&number).then_some(number)
 }
@@ -13,7 +13,7 @@ const POINTER: &str = "list_ptr";
 use crate::{
     backend::{compiler::Compiler, RamPage, RegisterContents},
     err,
-    frontend::{ExpressionType, Range},
+    frontend::{Expression, ExpressionType, Range},
     instr, modul,
 };
 
@@ -27,9 +27,7 @@ pub fn init(compiler: &mut Compiler, location: Range) -> Res {
         });
     }
 
-    let slot: u8 = find_pointer_var_slot(&compiler.variables, location)?
-        .try_into()
-        .unwrap();
+    let slot = compiler.insert_temp_var(location)?;
     compiler.module_state.insert(POINTER, Box::from(slot));
     compiler.module_state.insert(INIT, Box::from(true));
     Ok(())
@@ -39,16 +37,25 @@ modul!(add pop get_pointer set_pointer last at);
 
 fn add(compiler: &mut Compiler, call: &Call) -> Res {
     let value = arg_parse(compiler, [Arg::Number("value")], call)?[0];
+    if let ExpressionType::ArrayLiteral(elements) = &value.typ {
+        return elements
+            .iter()
+            .try_for_each(|element| add_value(compiler, element, call.location));
+    }
+    add_value(compiler, value, call.location)
+}
+
+fn add_value(compiler: &mut Compiler, value: &Expression, location: Range) -> Res {
     let pointer = *compiler.get_module_state::<u8>(POINTER).unwrap();
     compiler.eval_expr(value)?;
     if compiler.last_scope().state.b != RegisterContents::Variable(pointer) {
-        instr!(compiler, LB, pointer, call.location);
+        instr!(compiler, LB, pointer, location);
     }
-    instr!(compiler, RC, call.location);
-    instr!(compiler, RW, call.location);
-    instr!(compiler, LAL, 1, call.location);
-    instr!(compiler, ADD, call.location);
-    instr!(compiler, SVA, pointer, call.location);
+    instr!(compiler, RC, location);
+    instr!(compiler, RW, location);
+    instr!(compiler, LAL, 1, location);
+    instr!(compiler, ADD, location);
+    instr!(compiler, SVA, pointer, location);
     Ok(())
 }
 
@@ -136,10 +143,3 @@ fn at(compiler: &mut Compiler, call: &Call) -> Res {
 fn is_initialized(compiler: &mut Compiler) -> bool {
     matches!(compiler.get_module_state(INIT), Some(true))
 }
-
-fn find_pointer_var_slot(slots: &[bool; 32], location: Range) -> Res<usize> {
-    slots.iter().rev().position(|slot| !*slot).ok_or(Error {
-        typ: Box::new(ErrorType::TooManyVars),
-        location,
-    })
-}
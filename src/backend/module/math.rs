@@ -0,0 +1,81 @@
+/*
+math.abs(x) # return
+math.min(a, b) # return
+math.max(a, b) # return
+*/
+
+use crate::{
+    backend::{compiler::Compiler, Instruction, InstructionVariant, RegisterContents},
+    err, instr, modul,
+};
+
+use super::{arg_parse, Arg, Call, ErrorType, Operand, Res};
+
+modul!(abs min max);
+
+fn abs(compiler: &mut Compiler, call: &Call) -> Res {
+    let [x] = arg_parse(compiler, [Arg::Number("x")], call)?;
+    let location = call.location;
+    if let Some(value) = compiler.try_get_constant(x) {
+        compiler.put_a_number(value.wrapping_abs(), location);
+        return Ok(());
+    }
+
+    compiler.eval_expr(x)?;
+    let end = compiler.insert_jump_mark();
+    compiler.put_b_number(0, location);
+    instr!(compiler, JGE, end, location);
+    // negative, so subtract it from zero
+    let temp = compiler.insert_temp_var(location)?;
+    instr!(compiler, SVA, temp, location);
+    compiler.put_a_number(0, location);
+    instr!(compiler, LB, temp, location);
+    instr!(compiler, SUB, location);
+    compiler.cleanup_temp_var(temp);
+    merge_paths(compiler, end);
+    Ok(())
+}
+
+fn min(compiler: &mut Compiler, call: &Call) -> Res {
+    select(compiler, call, InstructionVariant::JLE, i16::min)
+}
+
+fn max(compiler: &mut Compiler, call: &Call) -> Res {
+    select(compiler, call, InstructionVariant::JGE, i16::max)
+}
+
+/// leaves `a` in A if `keep_a` jumps, otherwise loads `b`
+fn select(
+    compiler: &mut Compiler,
+    call: &Call,
+    keep_a: InstructionVariant,
+    fold: fn(i16, i16) -> i16,
+) -> Res {
+    let [a, b] = arg_parse(compiler, [Arg::Number("a"), Arg::Number("b")], call)?;
+    let location = call.location;
+    let a = Operand::new(compiler, a, location)?;
+    let b = Operand::new(compiler, b, location)?;
+    if let (Operand::Constant(a), Operand::Constant(b)) = (&a, &b) {
+        compiler.put_a_number(fold(*a, *b), location);
+        return Ok(());
+    }
+
+    let end = compiler.insert_jump_mark();
+    b.put_into_b(compiler, location)?;
+    a.put_into_a(compiler, location)?;
+    compiler.push_instr(Instruction::new(keep_a, Some(end), location));
+    b.put_into_a(compiler, location)?;
+    merge_paths(compiler, end);
+
+    a.cleanup(compiler);
+    b.cleanup(compiler);
+    Ok(())
+}
+
+/// places `end`, where both branches meet with different registers
+fn merge_paths(compiler: &mut Compiler, end: u8) {
+    compiler.place_jump_mark(end);
+    let state = &mut compiler.last_scope_mut().state;
+    state.a = RegisterContents::Unknown;
+    state.b = RegisterContents::Unknown;
+}
@@ -1,24 +1,41 @@
 mod colorscreen;
 mod io;
 mod list;
+mod math;
 mod ram;
+mod random;
 mod screen;
 
 use crate::{
     err,
     error::Error,
-    frontend::{Expression, Range},
+    frontend::{Expression, ExpressionType, Range},
+    instr,
 };
 
-use super::{Compiler, ErrorType};
+use super::{Compiler, ComputerState, ErrorType};
 
 pub fn call(name: &str, compiler: &mut Compiler, call: &Call) -> Res {
+    if let Some(available) = methods(name) {
+        if !available.contains(&call.method_name.as_str()) {
+            return err!(
+                ErrorType::UnknownModuleMethod {
+                    module: name.to_string(),
+                    method: call.method_name.clone(),
+                    available,
+                },
+                call.location
+            );
+        }
+    }
     match name {
         "io" => io::module(compiler, call),
         "screen" => screen::module(compiler, call),
         "ram" => ram::module(compiler, call),
         "list" => list::module(compiler, call),
         "colorscreen" => colorscreen::module(compiler, call),
+        "math" => math::module(compiler, call),
+        "random" => random::module(compiler, call),
         _ => Err(Error {
             typ: Box::new(ErrorType::NonexistentModule(call.method_name.clone())),
             location: call.location,
@@ -27,25 +44,50 @@ pub fn call(name: &str, compiler: &mut Compiler, call: &Call) -> Res {
 }
 
 pub fn exist(name: &str) -> bool {
-    matches!(name, "io" | "screen" | "ram" | "list" | "colorscreen")
+    methods(name).is_some()
+}
+
+/// the methods of a module, `None` if there is no such module
+fn methods(name: &str) -> Option<&'static [&'static str]> {
+    Some(match name {
+        "io" => io::METHODS,
+        "screen" => screen::METHODS,
+        "ram" => ram::METHODS,
+        "list" => list::METHODS,
+        "colorscreen" => colorscreen::METHODS,
+        "math" => math::METHODS,
+        "random" => random::METHODS,
+        _ => return None,
+    })
 }
 
 pub fn init(name: &str, compiler: &mut Compiler, location: Range) -> Res {
     match name {
         "list" => list::init(compiler, location),
+        "random" => random::init(compiler, location),
         _ => Ok(()),
     }
 }
 
+/// the value of a constant like `colorscreen.red`, if the module has it
+pub fn constant(name: &str, property: &str) -> Option<i16> {
+    match name {
+        "colorscreen" => colorscreen::constant(property),
+        "io" => io::constant(property),
+        _ => None,
+    }
+}
+
 pub struct Call<'a> {
     pub method_name: &'a String,
-    pub args: &'a Vec<Expression>,
+    pub args: &'a [Expression],
     pub location: Range,
 }
 
 enum Arg {
     #[allow(dead_code)]
     Number(&'static str),
+    #[allow(dead_code)]
     Constant(&'static str),
 }
 
@@ -54,9 +96,13 @@ fn arg_parse<'a, const COUNT: usize>(
     types: [Arg; COUNT],
     call: &'a Call,
 ) -> Res<[&'a Expression; COUNT]> {
-    if types.len() != call.args.len() {
+    if COUNT != call.args.len() {
         return err!(
-            ErrorType::InvalidArgs("Wrong number of Arguments".to_string()),
+            ErrorType::WrongArgCount {
+                name: call.method_name.clone(),
+                expected: COUNT,
+                got: call.args.len(),
+            },
             call.location
         );
     }
@@ -80,9 +126,64 @@ fn arg_parse<'a, const COUNT: usize>(
     Ok(res)
 }
 
+/// a value that has to be loaded into a register more than once
+enum Operand<'a> {
+    Constant(i16),
+    Variable(&'a Expression),
+    Temp(u8),
+}
+
+impl<'a> Operand<'a> {
+    /// evaluates `expr` into a temp slot unless it is cheap to load
+    fn new(compiler: &mut Compiler, expr: &'a Expression, location: Range) -> Res<Self> {
+        if let Some(value) = compiler.try_get_constant(expr) {
+            return Ok(Self::Constant(value));
+        }
+        if matches!(expr.typ, ExpressionType::Identifier(..)) {
+            return Ok(Self::Variable(expr));
+        }
+        compiler.eval_expr(expr)?;
+        let temp = compiler.insert_temp_var(location)?;
+        compiler.save_to(temp, location);
+        Ok(Self::Temp(temp))
+    }
+
+    fn put_into_a(&self, compiler: &mut Compiler, location: Range) -> Res {
+        match self {
+            Self::Constant(value) => compiler.put_a_number(*value, location),
+            Self::Variable(expr) => compiler.put_into_a(expr)?,
+            Self::Temp(slot) => instr!(compiler, LA, *slot, location),
+        }
+        Ok(())
+    }
+
+    fn put_into_b(&self, compiler: &mut Compiler, location: Range) -> Res {
+        match self {
+            Self::Constant(value) => compiler.put_b_number(*value, location),
+            Self::Variable(expr) => compiler.put_into_b(expr)?,
+            Self::Temp(slot) => instr!(compiler, LB, *slot, location),
+        }
+        Ok(())
+    }
+
+    const fn cleanup(self, compiler: &mut Compiler) {
+        if let Self::Temp(slot) = self {
+            compiler.cleanup_temp_var(slot);
+        }
+    }
+}
+
+/// places `mark` where paths with different registers meet
+fn place_merge(compiler: &mut Compiler, mark: u8) {
+    compiler.place_jump_mark(mark);
+    compiler.last_scope_mut().state = ComputerState::UNKNOWN;
+}
+
 #[macro_export]
 macro_rules! modul {
     ( $($method:ident)* ) => {
+        pub const METHODS: &[&str] = &[$(stringify!($method)),*];
+
         pub fn module(compiler: &mut Compiler, call: &Call) -> Res {
             match call.method_name.as_str() {
                 $(stringify!($method) => $method(compiler, call),)*
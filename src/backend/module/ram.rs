@@ -2,18 +2,88 @@
 ram.read(where) # return
 ram.write(what, where) # also return
 ram.copy(from, to) # also return
+ram.fill(value, from, to)
 */
 
 use crate::{
-    backend::{compiler::Compiler, RamPage},
+    backend::{compiler::Compiler, ComputerState, RamPage},
     err,
     frontend::{Expression, ExpressionType, Range},
     instr, modul,
 };
 
-use super::{arg_parse, Arg, Call, ErrorType, Res};
+use super::{arg_parse, Arg, Call, ErrorType, Operand, Res};
 
-modul!(read write copy);
+modul!(read write copy fill);
+
+/// constant spans up to this length get unrolled
+const FILL_UNROLL_LIMIT: i16 = 4;
+
+/// writes `value` to every address from `from` to `to`, both included,
+/// nothing if `to` is below `from`
+fn fill(compiler: &mut Compiler, call: &Call) -> Res {
+    let [value, from, to] = arg_parse(
+        compiler,
+        [Arg::Number("value"), Arg::Number("from"), Arg::Number("to")],
+        call,
+    )?;
+    let location = call.location;
+    let value = Operand::new(compiler, value, location)?;
+
+    let bounds = compiler
+        .try_get_constant(from)
+        .zip(compiler.try_get_constant(to));
+    if let Some((from, to)) = bounds {
+        // an empty range unrolls to nothing
+        if i32::from(to) - i32::from(from) < i32::from(FILL_UNROLL_LIMIT) {
+            for address in from..=to {
+                let address = Expression {
+                    typ: ExpressionType::NumericLiteral(address),
+                    location,
+                };
+                put_address(compiler, &address, location)?;
+                value.put_into_a(compiler, location)?;
+                instr!(compiler, RW, location);
+            }
+            value.cleanup(compiler);
+            return Ok(());
+        }
+    }
+
+    let to = Operand::new(compiler, to, location)?;
+    match compiler.try_get_constant(from) {
+        Some(from) => compiler.put_b_number(from, location),
+        None if Compiler::can_put_into_b(from) => compiler.put_into_b(from)?,
+        None => {
+            compiler.eval_expr(from)?;
+            compiler.switch(location)?;
+        }
+    }
+
+    let end = compiler.insert_jump_mark();
+    if bounds.is_none() {
+        to.put_into_a(compiler, location)?;
+        instr!(compiler, JL, end, location);
+    }
+
+    // B holds the address, the back-edge arrives with a different one
+    let start = compiler.mark_here();
+    compiler.last_scope_mut().state = ComputerState::UNKNOWN;
+    instr!(compiler, RC, location);
+    value.put_into_a(compiler, location)?;
+    instr!(compiler, RW, location);
+    // checked before INB, which would wrap after the last address
+    to.put_into_a(compiler, location)?;
+    instr!(compiler, JE, end, location);
+    instr!(compiler, INB, location);
+    instr!(compiler, JMP, start, location);
+    compiler.place_jump_mark(end);
+    compiler.last_scope_mut().state = ComputerState::UNKNOWN;
+
+    value.cleanup(compiler);
+    to.cleanup(compiler);
+    Ok(())
+}
 
 fn copy(compiler: &mut Compiler, call: &Call) -> Res {
     let [from, to] = arg_parse(compiler, [Arg::Number("from"), Arg::Number("to")], call)?;
@@ -88,6 +158,13 @@ fn put_address(compiler: &mut Compiler, address: &Expression, location: Range) -
             instr!(compiler, RC, location);
         }
         compiler.put_b_number(value, location);
+    } else if let Some(slot) = variable_slot(compiler, address) {
+        // the page only changes when the variable does
+        if compiler.last_scope().state.ram_page != RamPage::OfVariable(slot) {
+            instr!(compiler, RC, location);
+        }
+        compiler.put_into_b(address)?;
+        compiler.last_scope_mut().state.ram_page = RamPage::OfVariable(slot);
     } else {
         instr!(compiler, RC, location);
         if Compiler::can_put_into_b(address) {
@@ -111,3 +188,10 @@ fn put_address(compiler: &mut Compiler, address: &Expression, location: Range) -
     }
     Ok(())
 }
+
+fn variable_slot(compiler: &Compiler, address: &Expression) -> Option<u8> {
+    match &address.typ {
+        ExpressionType::Identifier(symbol) => compiler.get_var_noerror(symbol),
+        _ => None,
+    }
+}
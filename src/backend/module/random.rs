@@ -0,0 +1,68 @@
+/*
+random.next() # return
+random.seed(x)
+*/
+
+const STATE: &str = "random_state";
+
+/// x^16 + x^5 + x^3 + x^2 + 1, a maximal-length LFSR shifting left
+const TAPS: i16 = 0x002D;
+/// any value but 0 works, 0 would stay 0 forever
+const DEFAULT_SEED: i16 = 0x1234;
+
+use crate::{backend::compiler::Compiler, err, frontend::Range, instr, modul};
+
+use super::{arg_parse, place_merge, Arg, Call, Error, ErrorType, Res};
+
+pub fn init(compiler: &mut Compiler, location: Range) -> Res {
+    if compiler.get_module_state::<u8>(STATE).is_some() {
+        return Err(Error {
+            typ: Box::new(ErrorType::ModuleInitTwice("random".to_string())),
+            location,
+        });
+    }
+
+    let slot = compiler.insert_temp_var(location)?;
+    compiler.module_state.insert(STATE, Box::from(slot));
+    compiler.put_a_number(DEFAULT_SEED, location);
+    instr!(compiler, SVA, slot, location);
+    Ok(())
+}
+
+modul!(next seed);
+
+/// shifts the state left, xoring in the taps if the top bit fell out
+fn next(compiler: &mut Compiler, call: &Call) -> Res {
+    arg_parse(compiler, [], call)?;
+    let state = *compiler.get_module_state::<u8>(STATE).unwrap();
+    let location = call.location;
+
+    let top_clear = compiler.insert_jump_mark();
+    let end = compiler.insert_jump_mark();
+    instr!(compiler, LA, state, location);
+    compiler.put_b_number(0, location);
+    instr!(compiler, JGE, top_clear, location);
+    instr!(compiler, SUP, 1, location);
+    compiler.put_b_number(TAPS, location);
+    instr!(compiler, XOR, location);
+    instr!(compiler, JMP, end, location);
+    place_merge(compiler, top_clear);
+    instr!(compiler, SUP, 1, location);
+    place_merge(compiler, end);
+    instr!(compiler, SVA, state, location);
+    Ok(())
+}
+
+fn seed(compiler: &mut Compiler, call: &Call) -> Res {
+    let [seed] = arg_parse(compiler, [Arg::Number("seed")], call)?;
+    if compiler.try_get_constant(seed) == Some(0) {
+        return err!(
+            ErrorType::InvalidArgs("The seed can't be 0".to_string()),
+            seed.location
+        );
+    }
+    let state = *compiler.get_module_state::<u8>(STATE).unwrap();
+    compiler.eval_expr(seed)?;
+    instr!(compiler, SVA, state, call.location);
+    Ok(())
+}
@@ -1,10 +1,11 @@
 use crate::{
-    backend::compiler::Compiler,
+    backend::{compiler::Compiler, ComputerState, Instruction, InstructionVariant},
+    err,
     frontend::{Expression, Range},
     instr,
 };
 
-use super::{arg_parse, Arg, Call, Error, ErrorType, Res};
+use super::{arg_parse, place_merge, Arg, Call, Error, ErrorType, Operand, Res};
 
 /*
 Screen:
@@ -21,16 +22,36 @@ const BASE_OUT_REG: u8 = 32;
 const SCREENOP_REG: u8 = BASE_OUT_REG + 6;
 const SCREENPOS_REG: u8 = BASE_OUT_REG + 7;
 
+/// both screens are 64 pixels wide and high
+const SCREEN_SIZE: i16 = 64;
+
+pub const METHODS: &[&str] = &[
+    "flip",
+    "clear",
+    "present",
+    "set_at",
+    "invert_at",
+    "off_at",
+    "set",
+    "invert",
+    "off",
+    "line",
+    "rect",
+];
+
 pub fn module(compiler: &mut Compiler, call: &Call) -> Res {
     match call.method_name.as_str() {
         "flip" => screen_operation(compiler, call, 1),
         "clear" => screen_operation(compiler, call, 2),
+        "present" => present(compiler, call),
         "set_at" => pixel_operation(compiler, call, 4),
         "invert_at" => pixel_operation(compiler, call, 8),
         "off_at" => pixel_operation(compiler, call, 16),
         "set" => whole_pixel_operation(compiler, call, 4),
         "invert" => whole_pixel_operation(compiler, call, 8),
         "off" => whole_pixel_operation(compiler, call, 16),
+        "line" => line(compiler, call),
+        "rect" => rect(compiler, call),
         _ => Err(Error {
             typ: Box::new(ErrorType::UnknownMethod(call.method_name.clone())),
             location: call.location,
@@ -52,6 +73,15 @@ fn screen_operation(compiler: &mut Compiler, call: &Call, op: u8) -> Res {
     Ok(())
 }
 
+/// shows the drawn frame and starts the next one on an empty buffer,
+/// emits `LAL 1, SVA 38, LAL 2, SVA 38` (flip, then clear)
+fn present(compiler: &mut Compiler, call: &Call) -> Res {
+    arg_parse(compiler, [], call)?;
+    write_screenop(compiler, 1, call.location);
+    write_screenop(compiler, 2, call.location);
+    Ok(())
+}
+
 fn whole_pixel_operation(compiler: &mut Compiler, call: &Call, op: u8) -> Res {
     let args = arg_parse(compiler, [Arg::Number("pos")], call)?;
 
@@ -62,6 +92,324 @@ fn whole_pixel_operation(compiler: &mut Compiler, call: &Call, op: u8) -> Res {
     Ok(())
 }
 
+fn line(compiler: &mut Compiler, call: &Call) -> Res {
+    let ends = arg_parse(
+        compiler,
+        [
+            Arg::Number("x1"),
+            Arg::Number("y1"),
+            Arg::Number("x2"),
+            Arg::Number("y2"),
+        ],
+        call,
+    )?;
+    check_coords(compiler, &ends)?;
+    draw_line(compiler, ends, call.location, |compiler, x, y| {
+        plot(compiler, x, y, 4, call.location)
+    })
+}
+
+/// draws the outline of the rectangle with the corners `(x1, y1)` and
+/// `(x2, y2)`, at runtime `x1 <= x2` and `y1 <= y2` is expected
+fn rect(compiler: &mut Compiler, call: &Call) -> Res {
+    let args = arg_parse(
+        compiler,
+        [
+            Arg::Number("x1"),
+            Arg::Number("y1"),
+            Arg::Number("x2"),
+            Arg::Number("y2"),
+        ],
+        call,
+    )?;
+    check_coords(compiler, &args)?;
+    let location = call.location;
+    let corners = args.map(|arg| compiler.try_get_constant(arg));
+    if let [Some(x1), Some(y1), Some(x2), Some(y2)] = corners {
+        let (left, right) = (x1.min(x2), x1.max(x2));
+        let (top, bottom) = (y1.min(y2), y1.max(y2));
+        let rows = (left..=right).flat_map(|x| [(x, top), (x, bottom)]);
+        let columns = (top + 1..bottom).flat_map(|y| [(left, y), (right, y)]);
+        let mut drawn = vec![];
+        for (x, y) in rows.chain(columns) {
+            if !drawn.contains(&(x, y)) {
+                plot(
+                    compiler,
+                    &Operand::Constant(x),
+                    &Operand::Constant(y),
+                    4,
+                    location,
+                )?;
+                drawn.push((x, y));
+            }
+        }
+        return Ok(());
+    }
+
+    let [x1, y1, x2, y2] = args;
+    let x1 = Operand::new(compiler, x1, location)?;
+    let y1 = Operand::new(compiler, y1, location)?;
+    let x2 = Operand::new(compiler, x2, location)?;
+    let y2 = Operand::new(compiler, y2, location)?;
+    let i = compiler.insert_temp_var(location)?;
+    edge_loop(compiler, i, &x1, &x2, location, |compiler, i| {
+        plot(compiler, i, &y1, 4, location)?;
+        plot(compiler, i, &y2, 4, location)
+    })?;
+    edge_loop(compiler, i, &y1, &y2, location, |compiler, i| {
+        plot(compiler, &x1, i, 4, location)?;
+        plot(compiler, &x2, i, 4, location)
+    })?;
+    compiler.cleanup_temp_var(i);
+    for corner in [x1, y1, x2, y2] {
+        corner.cleanup(compiler);
+    }
+    Ok(())
+}
+
+/// counts `i` from the smaller of `from` and `to` up to the larger one,
+/// calling `body` each time
+pub fn edge_loop(
+    compiler: &mut Compiler,
+    i: u8,
+    from: &Operand,
+    to: &Operand,
+    location: Range,
+    body: impl FnOnce(&mut Compiler, &Operand) -> Res,
+) -> Res {
+    let last = compiler.insert_temp_var(location)?;
+    let reversed = compiler.insert_jump_mark();
+    let start = compiler.insert_jump_mark();
+    from.put_into_a(compiler, location)?;
+    to.put_into_b(compiler, location)?;
+    instr!(compiler, JG, reversed, location);
+    instr!(compiler, SVA, i, location);
+    to.put_into_a(compiler, location)?;
+    instr!(compiler, SVA, last, location);
+    instr!(compiler, JMP, start, location);
+    place_merge(compiler, reversed);
+    instr!(compiler, SVA, last, location);
+    to.put_into_a(compiler, location)?;
+    instr!(compiler, SVA, i, location);
+    place_merge(compiler, start);
+    body(compiler, &Operand::Temp(i))?;
+    instr!(compiler, LA, i, location);
+    compiler.put_b_number(1, location);
+    instr!(compiler, ADD, location);
+    instr!(compiler, SVA, i, location);
+    instr!(compiler, LB, last, location);
+    instr!(compiler, JLE, start, location);
+    compiler.cleanup_temp_var(last);
+    Ok(())
+}
+
+/// applies the pixel operation `op` at `(x, y)`
+fn plot(compiler: &mut Compiler, x: &Operand, y: &Operand, op: u8, location: Range) -> Res {
+    if let (Operand::Constant(x), Operand::Constant(y)) = (x, y) {
+        compiler.put_a_number(x << 8 | y, location);
+    } else {
+        x.put_into_a(compiler, location)?;
+        instr!(compiler, SUP, 8, location);
+        y.put_into_b(compiler, location)?;
+        instr!(compiler, OR, location);
+    }
+    instr!(compiler, SVA, SCREENPOS_REG, location);
+    write_screenop(compiler, op, location);
+    Ok(())
+}
+
+/// Calls `plot` for every pixel of the line from `(x1, y1)` to `(x2, y2)`.
+///
+/// Constant ends are unrolled, otherwise this emits a Bresenham loop
+/// and `plot` gets the temp slots holding the current pixel
+pub fn draw_line(
+    compiler: &mut Compiler,
+    [x1, y1, x2, y2]: [&Expression; 4],
+    location: Range,
+    mut plot: impl FnMut(&mut Compiler, &Operand, &Operand) -> Res,
+) -> Res {
+    let ends = [x1, y1, x2, y2].map(|end| compiler.try_get_constant(end));
+    if let [Some(x1), Some(y1), Some(x2), Some(y2)] = ends {
+        for (x, y) in line_points(x1, y1, x2, y2) {
+            plot(compiler, &Operand::Constant(x), &Operand::Constant(y))?;
+        }
+        return Ok(());
+    }
+
+    let x2 = Operand::new(compiler, x2, location)?;
+    let y2 = Operand::new(compiler, y2, location)?;
+    let vars = LineVars::new(compiler, x1, y1, &x2, &y2, location)?;
+
+    let start = compiler.mark_here();
+    compiler.last_scope_mut().state = ComputerState::UNKNOWN;
+    plot(compiler, &Operand::Temp(vars.x), &Operand::Temp(vars.y))?;
+
+    // stop after plotting the last pixel
+    let end = compiler.insert_jump_mark();
+    let next = compiler.insert_jump_mark();
+    instr!(compiler, LA, vars.x, location);
+    x2.put_into_b(compiler, location)?;
+    instr!(compiler, JNE, next, location);
+    instr!(compiler, LA, vars.y, location);
+    y2.put_into_b(compiler, location)?;
+    instr!(compiler, JE, end, location);
+    place_merge(compiler, next);
+
+    vars.step(compiler, location);
+    instr!(compiler, JMP, start, location);
+    place_merge(compiler, end);
+
+    vars.cleanup(compiler);
+    x2.cleanup(compiler);
+    y2.cleanup(compiler);
+    Ok(())
+}
+
+/// every pixel on the line from `(x1, y1)` to `(x2, y2)`, both ends included
+fn line_points(x1: i16, y1: i16, x2: i16, y2: i16) -> Vec<(i16, i16)> {
+    let (dx, sx) = ((x2 - x1).abs(), if x1 < x2 { 1 } else { -1 });
+    let (dy, sy) = (-(y2 - y1).abs(), if y1 < y2 { 1 } else { -1 });
+    let (mut x, mut y, mut err) = (x1, y1, dx + dy);
+    let mut points = vec![];
+    loop {
+        points.push((x, y));
+        if x == x2 && y == y2 {
+            return points;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+/// the temp slots of a line drawn at runtime, `dy` is negative
+struct LineVars {
+    x: u8,
+    y: u8,
+    dx: u8,
+    dy: u8,
+    sx: u8,
+    sy: u8,
+    err: u8,
+    e2: u8,
+}
+
+impl LineVars {
+    fn new(
+        compiler: &mut Compiler,
+        x1: &Expression,
+        y1: &Expression,
+        x2: &Operand,
+        y2: &Operand,
+        location: Range,
+    ) -> Res<Self> {
+        let mut slots = [0; 8];
+        for slot in &mut slots {
+            *slot = compiler.insert_temp_var(location)?;
+        }
+        let [x, y, dx, dy, sx, sy, err, e2] = slots;
+        let vars = Self {
+            x,
+            y,
+            dx,
+            dy,
+            sx,
+            sy,
+            err,
+            e2,
+        };
+
+        compiler.eval_expr(x1)?;
+        instr!(compiler, SVA, x, location);
+        compiler.eval_expr(y1)?;
+        instr!(compiler, SVA, y, location);
+
+        x2.put_into_a(compiler, location)?;
+        instr!(compiler, LB, x, location);
+        instr!(compiler, SUB, location);
+        step_direction(compiler, dx, sx, InstructionVariant::JGE, location);
+
+        instr!(compiler, LA, y, location);
+        y2.put_into_b(compiler, location)?;
+        instr!(compiler, SUB, location);
+        step_direction(compiler, dy, sy, InstructionVariant::JLE, location);
+
+        instr!(compiler, LA, dx, location);
+        instr!(compiler, LB, dy, location);
+        instr!(compiler, ADD, location);
+        instr!(compiler, SVA, err, location);
+        Ok(vars)
+    }
+
+    /// moves to the next pixel
+    fn step(&self, compiler: &mut Compiler, location: Range) {
+        let skip_x = compiler.insert_jump_mark();
+        let skip_y = compiler.insert_jump_mark();
+        instr!(compiler, LA, self.err, location);
+        instr!(compiler, SUP, 1, location);
+        instr!(compiler, SVA, self.e2, location);
+        instr!(compiler, LB, self.dy, location);
+        instr!(compiler, JL, skip_x, location);
+        add_to(compiler, self.err, self.dy, location);
+        add_to(compiler, self.x, self.sx, location);
+        place_merge(compiler, skip_x);
+
+        instr!(compiler, LA, self.e2, location);
+        instr!(compiler, LB, self.dx, location);
+        instr!(compiler, JG, skip_y, location);
+        add_to(compiler, self.err, self.dx, location);
+        add_to(compiler, self.y, self.sy, location);
+        place_merge(compiler, skip_y);
+    }
+
+    fn cleanup(&self, compiler: &mut Compiler) {
+        for slot in [
+            self.x, self.y, self.dx, self.dy, self.sx, self.sy, self.err, self.e2,
+        ] {
+            compiler.cleanup_temp_var(slot);
+        }
+    }
+}
+
+/// Saves the difference in A to `diff` and sets `step` to 1 if `keep`
+/// holds against 0, otherwise negates `diff` and sets `step` to -1
+fn step_direction(
+    compiler: &mut Compiler,
+    diff: u8,
+    step: u8,
+    keep: InstructionVariant,
+    location: Range,
+) {
+    let done = compiler.insert_jump_mark();
+    instr!(compiler, SVA, diff, location);
+    compiler.put_a_number(1, location);
+    instr!(compiler, SVA, step, location);
+    instr!(compiler, LA, diff, location);
+    compiler.put_b_number(0, location);
+    compiler.push_instr(Instruction::new(keep, Some(done), location));
+    compiler.put_a_number(0, location);
+    instr!(compiler, LB, diff, location);
+    instr!(compiler, SUB, location);
+    instr!(compiler, SVA, diff, location);
+    compiler.put_a_number(-1, location);
+    instr!(compiler, SVA, step, location);
+    place_merge(compiler, done);
+}
+
+/// `slot += by`
+fn add_to(compiler: &mut Compiler, slot: u8, by: u8, location: Range) {
+    instr!(compiler, LA, slot, location);
+    instr!(compiler, LB, by, location);
+    instr!(compiler, ADD, location);
+    instr!(compiler, SVA, slot, location);
+}
+
 fn write_screenpos(
     compiler: &mut Compiler,
     x: &Expression,
@@ -73,6 +421,19 @@ fn write_screenpos(
     Ok(())
 }
 
+/// errors at the first coordinate known at compile-time that is off the screen
+pub fn check_coords(compiler: &mut Compiler, coords: &[&Expression]) -> Res {
+    for coord in coords {
+        if let Some(value) = compiler
+            .try_get_constant(coord)
+            .filter(|value| !(0..SCREEN_SIZE).contains(value))
+        {
+            return err!(ErrorType::OffScreen(value), coord.location);
+        }
+    }
+    Ok(())
+}
+
 pub fn put_xy(
     compiler: &mut Compiler,
     upper: &Expression,
@@ -80,6 +441,7 @@ pub fn put_xy(
     location: Range,
     offset: u8,
 ) -> Res {
+    check_coords(compiler, &[upper, lower])?;
     match (
         compiler.try_get_constant(upper),
         compiler.try_get_constant(lower),
@@ -0,0 +1,147 @@
+use crate::{
+    err,
+    error::{Error, TAB_WIDTH},
+    frontend::{Location, Range},
+    warning::Category,
+};
+
+use super::ErrorType;
+
+/// An optimization, run while lowering statements or on the finished code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    ConstantFolding,
+    StrengthReduction,
+    CommonSubexpressions,
+    RemoveUnreachable,
+    RemoveJumpsToNext,
+    RemoveRedundantLoads,
+}
+
+/// every optimization pass with the level it's enabled at, in the order they run
+const PASSES: [(u8, Pass); 6] = [
+    (1, Pass::ConstantFolding),
+    (1, Pass::StrengthReduction),
+    (1, Pass::CommonSubexpressions),
+    (1, Pass::RemoveUnreachable),
+    (1, Pass::RemoveJumpsToNext),
+    (2, Pass::RemoveRedundantLoads),
+];
+
+impl Pass {
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::ConstantFolding => "constant-folding",
+            Self::StrengthReduction => "strength-reduction",
+            Self::CommonSubexpressions => "common-subexpressions",
+            Self::RemoveUnreachable => "remove-unreachable",
+            Self::RemoveJumpsToNext => "remove-jumps-to-next",
+            Self::RemoveRedundantLoads => "remove-redundant-loads",
+        }
+    }
+}
+
+/// Settings for a compilation, set by a pragma line or CLI flags
+///
+/// `optimize` levels:
+/// - 0: no cleanup passes, exactly what the statements lower to
+/// - 1: fold constant expressions, shifts instead of multiplications, compute
+///   repeated parts of an assignment once, remove unreachable code and jumps
+///   to the next instruction
+/// - 2: also remove redundant loads
+///
+/// Warnings of a category in `werror` fail the compilation
+///
+/// `tab_width` is how many columns a tab takes when errors print their line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub optimize: u8,
+    pub werror: Vec<Category>,
+    pub tab_width: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            optimize: Self::MAX_OPTIMIZE,
+            werror: vec![],
+            tab_width: TAB_WIDTH,
+        }
+    }
+}
+
+impl Options {
+    pub const MAX_OPTIMIZE: u8 = 2;
+    pub const MAX_TAB_WIDTH: usize = 16;
+
+    /// Reads options from a `# pragma key=value ...` comment before the first
+    /// token, falling back to the defaults
+    ///
+    /// # Errors
+    ///
+    /// if the pragma has an unknown key or an invalid value
+    pub fn from_pragma(code: &str) -> Result<Self, Error> {
+        let mut options = Self::default();
+        for (number, line) in code.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some(comment) = line.strip_prefix('#') else {
+                break;
+            };
+            let Some(pragma) = comment.trim_start().strip_prefix("pragma ") else {
+                continue;
+            };
+            let location = Range(
+                Location(number as u16, 1),
+                Location(number as u16, line.len() as u16),
+            );
+            for setting in pragma.split_whitespace() {
+                options.set(setting, location)?;
+            }
+        }
+        Ok(options)
+    }
+
+    /// the passes that run at this optimization level
+    #[must_use]
+    pub fn passes(&self) -> Vec<Pass> {
+        PASSES
+            .into_iter()
+            .filter(|(level, _)| self.optimize >= *level)
+            .map(|(_, pass)| pass)
+            .collect()
+    }
+
+    /// whether `pass` runs at this optimization level
+    #[must_use]
+    pub fn runs(&self, pass: Pass) -> bool {
+        self.passes().contains(&pass)
+    }
+
+    /// applies a single `key=value` setting
+    ///
+    /// # Errors
+    ///
+    /// if the key is unknown or the value is invalid
+    pub fn set(&mut self, setting: &str, location: Range) -> Result<(), Error> {
+        let invalid = |reason: String| err!(ErrorType::InvalidPragma(reason), location);
+        let Some((key, value)) = setting.split_once('=') else {
+            return invalid(format!("expected key=value, got {setting}"));
+        };
+        match key {
+            "optimize" => match value.parse() {
+                Ok(level) if level <= Self::MAX_OPTIMIZE => self.optimize = level,
+                _ => return invalid(format!("optimize has to be 0 to {}", Self::MAX_OPTIMIZE)),
+            },
+            "tab_width" => match value.parse() {
+                Ok(width) if (1..=Self::MAX_TAB_WIDTH).contains(&width) => self.tab_width = width,
+                _ => return invalid(format!("tab_width has to be 1 to {}", Self::MAX_TAB_WIDTH)),
+            },
+            _ => return invalid(format!("unknown option {key}")),
+        }
+        Ok(())
+    }
+}
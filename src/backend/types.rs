@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 
-use crate::backend::Instruction;
+use crate::{
+    backend::Instruction,
+    frontend::{Expression, Ident},
+};
 
 #[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
 #[allow(unused)]
@@ -12,9 +15,23 @@ pub enum RegisterContents {
     Unknown,
 }
 
+impl RegisterContents {
+    /// the contents if both paths leave the same ones
+    #[must_use]
+    pub fn merge(self, other: Self) -> Self {
+        if self == other {
+            self
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RamPage {
     ThisOne(u8),
+    /// the page of the address in this variable slot
+    OfVariable(u8),
     Unknown,
 }
 
@@ -24,7 +41,7 @@ impl Default for RamPage {
     }
 }
 
-#[derive(Default, Copy, Clone, Debug)]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
 pub struct ComputerState {
     pub a: RegisterContents,
     pub b: RegisterContents,
@@ -32,10 +49,63 @@ pub struct ComputerState {
     pub ram_page: RamPage,
 }
 
+impl ComputerState {
+    /// nothing is known, e.g. where different paths meet
+    pub const UNKNOWN: Self = Self {
+        a: RegisterContents::Unknown,
+        b: RegisterContents::Unknown,
+        c: RegisterContents::Unknown,
+        ram_page: RamPage::Unknown,
+    };
+
+    /// what is still known where two paths meet
+    #[must_use]
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            a: self.a.merge(other.a),
+            b: self.b.merge(other.b),
+            c: self.c.merge(other.c),
+            ram_page: if self.ram_page == other.ram_page {
+                self.ram_page
+            } else {
+                RamPage::Unknown
+            },
+        }
+    }
+}
+
+/// a user-defined function, inlined at every call
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub params: Vec<Ident>,
+    pub body: Vec<Expression>,
+}
+
+/// a region of RAM declared with `var name[size]`
+#[derive(Debug, Clone, Copy)]
+pub struct Array {
+    /// address of the first element
+    pub base: i16,
+    pub size: i16,
+}
+
+/// a loop that is currently being compiled
+#[derive(Debug)]
+pub struct Loop {
+    /// jump mark after the loop
+    pub end: u8,
+    /// jump mark where the next iteration starts
+    pub next: u8,
+    /// if a `break` jumps to `end`
+    pub broken: bool,
+    /// if a `continue` jumps to `next`
+    pub continued: bool,
+}
+
 #[derive(Debug)]
 pub enum Instr {
     Code(Instruction),
-    Scope(Vec<Instr>),
+    Scope(Vec<Self>),
 }
 
 #[derive(Debug, Default)]
@@ -43,7 +113,12 @@ pub struct Scope {
     pub state: ComputerState,
     pub(crate) variables: HashMap<String, u8>,
     pub(crate) inline_variables: HashMap<String, i16>,
+    pub(crate) arrays: HashMap<String, Array>,
+    /// variables of this scope that haven't been read yet
+    pub(crate) unread: Vec<Ident>,
     pub(crate) instructions: Vec<Instr>,
+    /// the index of the `repeat` this is the body of, it can't be assigned to
+    pub(crate) loop_index: Option<String>,
 }
 
 impl Scope {
@@ -1,5 +1,5 @@
 use colored::{Colorize, CustomColor};
-use std::fmt::{Debug, Display};
+use std::fmt::{Debug, Display, Write as _};
 
 use crate::frontend::Range;
 
@@ -41,18 +41,30 @@ impl Display for Error {
     }
 }
 
-const RED: CustomColor = CustomColor {
+pub const RED: CustomColor = CustomColor {
     r: 197,
     g: 15,
     b: 31,
 };
 
-const BRIGHT_RED: CustomColor = CustomColor {
+pub const BRIGHT_RED: CustomColor = CustomColor {
     r: 231,
     g: 72,
     b: 86,
 };
 
+pub const YELLOW: CustomColor = CustomColor {
+    r: 193,
+    g: 156,
+    b: 0,
+};
+
+pub const BRIGHT_YELLOW: CustomColor = CustomColor {
+    r: 249,
+    g: 241,
+    b: 165,
+};
+
 const BRIGHT_BLUE: CustomColor = CustomColor {
     r: 59,
     g: 120,
@@ -65,35 +77,138 @@ impl Error {
         Ok(())
     }
 
-    pub fn pretty_print(&self, code: &str, file: &str) {
-        if self.location.0 .0 != self.location.1 .0 {
-            println!("Multi-line errors don't support nice error messages yet\n{self}");
-            return;
-        }
-        let Some(line) = code.split('\n').nth(self.location.0 .0 as usize) else {
-            println!("Compiler crashed, line does not exist in file, apparently\n{self}");
-            return;
-        };
-
-        println!(
-            "{} {}\nat {file}:{:?}",
-            "Error:".custom_color(RED),
-            self.typ.get_message().custom_color(BRIGHT_RED),
-            self.location
-        );
+    /// one line of JSON for tools, see [`diagnostic_json`]
+    #[must_use]
+    pub fn to_json(&self, file: &str) -> String {
+        diagnostic_json("error", &self.typ.get_message(), self.location, file)
+    }
 
-        let line_number = format!("{} | ", self.location.0 .0 + 1);
-        let len = line_number.len() - 3;
-
-        println!("{} {} ", " ".repeat(len), "|".custom_color(BRIGHT_BLUE));
-        print!("{}", line_number.as_str().custom_color(BRIGHT_BLUE));
-        println!("{line}");
-        print!("{} {} ", " ".repeat(len), "|".custom_color(BRIGHT_BLUE));
-        println!(
-            "{}{}\n",
-            " ".repeat(self.location.0 .1 as usize - 1),
-            "^".repeat((self.location.1 .1 - self.location.0 .1) as usize + 1)
-                .custom_color(BRIGHT_RED)
+    /// prints the error with its line, tabs expanded to `tab_width`
+    pub fn pretty_print(&self, code: &str, file: &str, tab_width: usize) {
+        print_diagnostic(
+            ("Error:", RED, BRIGHT_RED),
+            &self.typ.get_message(),
+            self.location,
+            code,
+            file,
+            tab_width,
         );
     }
 }
+
+/// columns a tab advances to the next multiple of when printing a line, unless
+/// the pragma or `--tab-width` sets another width
+pub const TAB_WIDTH: usize = 4;
+
+/// prints `message` to stderr with the line of `code` it refers to and
+/// underlines `location`
+///
+/// `kind` is the label with its color and the color of the message,
+/// tabs in the line are expanded to `tab_width`
+pub fn print_diagnostic(
+    kind: (&str, CustomColor, CustomColor),
+    message: &str,
+    location: Range,
+    code: &str,
+    file: &str,
+    tab_width: usize,
+) {
+    let (label, color, highlight) = kind;
+    let kind = label.custom_color(color);
+    // generated code without a place in the source
+    if location == Range::default() {
+        eprintln!("{kind} {}\nin {file}\n", message.custom_color(highlight));
+        return;
+    }
+    if location.0 .0 != location.1 .0 {
+        eprintln!("Multi-line errors don't support nice error messages yet\n{kind} {message} at {location:?}");
+        return;
+    }
+    let Some(line) = code.split('\n').nth(location.0 .0 as usize) else {
+        eprintln!("Compiler crashed, line does not exist in file, apparently\n{kind} {message} at {location:?}");
+        return;
+    };
+
+    eprintln!(
+        "{kind} {}\nat {file}:{location:?}",
+        message.custom_color(highlight),
+    );
+
+    let line_number = format!("{} | ", location.0 .0 + 1);
+    let len = line_number.len() - 3;
+
+    eprintln!("{} {} ", " ".repeat(len), "|".custom_color(BRIGHT_BLUE));
+    eprint!("{}", line_number.as_str().custom_color(BRIGHT_BLUE));
+    eprintln!("{}", expand_tabs(line, tab_width));
+    eprint!("{} {} ", " ".repeat(len), "|".custom_color(BRIGHT_BLUE));
+    // columns count characters, so they have to be measured on the expanded line
+    let start = display_width(line, (location.0 .1 as usize).saturating_sub(1), tab_width);
+    let end = display_width(line, location.1 .1 as usize, tab_width);
+    eprintln!(
+        "{}{}\n",
+        " ".repeat(start),
+        "^".repeat(end.saturating_sub(start).max(1))
+            .custom_color(BRIGHT_RED)
+    );
+}
+
+/// Produces `{"severity", "message", "file", "start", "end"}`
+///
+/// `start` and `end` are inclusive `{"line", "column"}` pairs (lines 0-based, columns
+/// 1-based) like in [`crate::frontend::tokens_to_json`]
+#[must_use]
+pub fn diagnostic_json(severity: &str, message: &str, location: Range, file: &str) -> String {
+    let Range(start, end) = location;
+    format!(
+        "{{\"severity\":\"{severity}\",\"message\":{},\"file\":{},\"start\":{{\"line\":{},\"column\":{}}},\"end\":{{\"line\":{},\"column\":{}}}}}",
+        json_string(message),
+        json_string(file),
+        start.0,
+        start.1,
+        end.0,
+        end.1
+    )
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::from('"');
+    for char in text.chars() {
+        match char {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\t' => json.push_str("\\t"),
+            char if char.is_control() => {
+                let _ = write!(json, "\\u{:04x}", char as u32);
+            }
+            char => json.push(char),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// replaces each tab with spaces up to the next multiple of `tab_width`
+#[must_use]
+pub fn expand_tabs(line: &str, tab_width: usize) -> String {
+    let mut expanded = String::new();
+    let mut width = 0;
+    for char in line.chars() {
+        if char == '\t' {
+            let spaces = tab_width - width % tab_width;
+            expanded.push_str(&" ".repeat(spaces));
+            width += spaces;
+        } else {
+            expanded.push(char);
+            width += 1;
+        }
+    }
+    expanded
+}
+
+/// how wide the first `chars` characters of `line` are once tabs are expanded
+#[must_use]
+pub fn display_width(line: &str, chars: usize, tab_width: usize) -> usize {
+    let prefix: String = line.chars().take(chars).collect();
+    expand_tabs(&prefix, tab_width).chars().count()
+}
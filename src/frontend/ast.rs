@@ -8,13 +8,17 @@ pub struct Ident {
     pub location: Range,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub enum ExpressionType {
     InlineDeclaration {
         ident: Ident,
         value: Box<Expression>,
     },
-    Use(Vec1<Ident>),
+    /// `use a.b`, a single module can get an alias with `use a as b`
+    Use {
+        modules: Vec1<Ident>,
+        alias: Option<Ident>,
+    },
     Conditional {
         condition: Box<Expression>,
         body: Vec<Expression>,
@@ -28,8 +32,34 @@ pub enum ExpressionType {
         condition: Box<Expression>,
         body: Vec<Expression>,
     },
+    Match {
+        value: Box<Expression>,
+        arms: Vec<(Expression, Vec<Expression>)>,
+        alternate: Option<Vec<Expression>>,
+    },
+    ArrayDeclaration {
+        ident: Ident,
+        size: Box<Expression>,
+    },
+    DoWhile {
+        body: Vec<Expression>,
+        condition: Box<Expression>,
+    },
+    FunctionDefinition {
+        ident: Ident,
+        params: Vec<Ident>,
+        body: Vec<Expression>,
+    },
+    Repeat {
+        count: Box<Expression>,
+        index: Option<Ident>,
+        body: Vec<Expression>,
+    },
     #[default]
     Pass,
+    Break,
+    Continue,
+    Stop,
     BinaryExpr {
         left: Box<Expression>,
         right: Box<Expression>,
@@ -40,8 +70,25 @@ pub enum ExpressionType {
         right: Box<Expression>,
         operator: EqualityOperator,
     },
+    LogicalExpr {
+        left: Box<Expression>,
+        right: Box<Expression>,
+        operator: LogicalOperator,
+    },
     Identifier(String),
     NumericLiteral(i16),
+    /// an element of an array, stored in RAM
+    Index {
+        array: Ident,
+        index: Box<Expression>,
+    },
+    /// `[1, 2, 3]`, can be assigned to an array or added to a list
+    ArrayLiteral(Vec<Expression>),
+    IndexAssignment {
+        array: Ident,
+        index: Box<Expression>,
+        value: Box<Expression>,
+    },
     Assignment {
         ident: Ident,
         value: Box<Expression>,
@@ -51,6 +98,11 @@ pub enum ExpressionType {
         value: Box<Expression>,
         operator: Operator,
     },
+    /// `x++` or `x--`, evaluates to the new value like `x += 1`
+    Increment {
+        ident: Ident,
+        operator: Operator,
+    },
     VarDeclaration {
         ident: Ident,
     },
@@ -62,9 +114,16 @@ pub enum ExpressionType {
         args: Vec<Expression>,
         function: Box<Expression>,
     },
-    Debug,
+    /// `debug` or `debug(value)`, compiled to the marker `LAL 17`
+    ///
+    /// The marker overwrites A, so a value is put into B before it and A
+    /// is 17 afterwards
+    Debug(Option<Box<Expression>>),
+    Trace(Box<Expression>),
+    Assert(Box<Expression>),
 }
 
+#[derive(Clone)]
 pub struct Expression {
     pub typ: ExpressionType,
     pub location: Range,
@@ -85,6 +144,8 @@ pub enum Operator {
     Plus,
     Minus,
     Mult,
+    /// exponents below 1 give 1
+    Pow,
     And,
     Or,
     Xor,
@@ -94,7 +155,7 @@ impl Operator {
     #[inline]
     #[must_use]
     pub const fn is_commutative(self) -> bool {
-        !matches!(self, Self::Minus)
+        !matches!(self, Self::Minus | Self::Pow)
     }
 }
 
@@ -132,6 +193,26 @@ impl EqualityOperator {
             Self::LessEq => Self::GreaterEq,
         }
     }
+
+    /// evaluates the comparison for known values
+    #[must_use]
+    pub const fn holds(self, left: i16, right: i16) -> bool {
+        match self {
+            Self::EqualTo => left == right,
+            Self::NotEqual => left != right,
+            Self::Greater => left > right,
+            Self::GreaterEq => left >= right,
+            Self::Less => left < right,
+            Self::LessEq => left <= right,
+        }
+    }
+}
+
+/// combines conditions, the right one is only checked if it decides the outcome
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogicalOperator {
+    And,
+    Or,
 }
 
 #[must_use]
@@ -3,6 +3,12 @@ use crate::error::ErrorType;
 pub enum Type {
     EmptyBlock,
     MissingEnd,
+    MissingTimes,
+    MissingWhile,
+    MissingCondition,
+    MissingCase,
+    MissingColon,
+    ElifAfterElse,
     InvalidModuleName,
     InvalidDeclartion,
     InvalidAssignment,
@@ -10,7 +16,16 @@ pub enum Type {
     FunctionChaining,
     MissingOpenParen,
     MissingClosingParen,
+    MissingClosingBracket,
+    InvalidIndex,
     InvalidDot,
+    NestedMember,
+    InvalidMethodCall,
+    InvalidCall,
+    IncrementInExpression,
+    ChainedComparison,
+    MultipleAliased,
+    DuplicateParam,
     Eof,
     UnexpectedOther,
     ExpectedParen,
@@ -21,6 +36,12 @@ impl ErrorType for Type {
         match self {
             Self::EmptyBlock => "Cannot have empty block. Use 'pass'",
             Self::MissingEnd => "Missing end keyword",
+            Self::MissingTimes => "Expected 'times' after the repeat count",
+            Self::MissingWhile => "Expected 'while' after the do block",
+            Self::MissingCondition => "Expected a condition on the same line",
+            Self::MissingCase => "Expected 'case' after the matched value",
+            Self::MissingColon => "Expected ':' after the case value",
+            Self::ElifAfterElse => "'elif' cannot follow 'else'",
             Self::InvalidModuleName => "Invalid module name",
             Self::InvalidDeclartion => "Expected identifier",
             Self::InvalidAssignment => "Can only assign to identifiers",
@@ -30,7 +51,18 @@ impl ErrorType for Type {
             }
             Self::MissingOpenParen => "Expected '(' after function call",
             Self::MissingClosingParen => "Missing ')'",
+            Self::MissingClosingBracket => "Missing ']'",
+            Self::InvalidIndex => "Only arrays can be indexed",
             Self::InvalidDot => "Cannot use . on this",
+            Self::NestedMember => "Modules only have methods, they can't be nested",
+            Self::InvalidMethodCall => "Only modules have methods",
+            Self::InvalidCall => "Only functions and methods can be called",
+            Self::IncrementInExpression => {
+                "'++' and '--' can't be part of a larger expression, use them on their own"
+            }
+            Self::ChainedComparison => "Comparisons can't be chained, use 'and' to combine them",
+            Self::MultipleAliased => "Only a single module can get an alias",
+            Self::DuplicateParam => "A function can't have two params with the same name",
             Self::Eof => "Unexpected EOF while parsing",
             Self::UnexpectedOther => "Unexpected token found",
             Self::ExpectedParen => "Unexpected token, expected ')'",
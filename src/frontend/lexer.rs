@@ -1,4 +1,4 @@
-use std::{fmt::Debug, iter::Peekable};
+use std::{fmt::Debug, iter::Peekable, num::IntErrorKind};
 
 use crate::{err, error::Error};
 
@@ -12,20 +12,39 @@ pub enum TokenType {
     OpenParen,
     OpenFuncParen,
     CloseParen,
+    OpenBracket,
+    CloseBracket,
     Comma,
+    Colon,
     Dot,
     BinaryOperator(Operator),
     IOperator(Operator),
+    Increment,
+    Decrement,
     EqOperator(EqOp),
     Inline,
     If,
     Elif,
+    Match,
+    Case,
     Else,
     End,
     Forever,
     While,
+    Do,
+    Repeat,
+    Times,
+    As,
     Pass,
+    Break,
+    Continue,
+    Stop,
+    And,
+    Or,
+    Fn,
     Debug,
+    Trace,
+    Assert,
     Use,
     Var,
     Eof,
@@ -38,14 +57,14 @@ pub struct Token {
 }
 
 impl Token {
-    fn from_char(typ: TokenType, location: Location) -> Self {
+    const fn from_char(typ: TokenType, location: Location) -> Self {
         Self {
             typ,
             location: Range::single_char(location),
         }
     }
 
-    fn with_len(typ: TokenType, location: Location, len: u16) -> Self {
+    const fn with_len(typ: TokenType, location: Location, len: u16) -> Self {
         Self {
             typ,
             location: Range(location, Location(location.0, location.1 + len - 1)),
@@ -53,6 +72,207 @@ impl Token {
     }
 }
 
+impl TokenType {
+    /// Category used for editor semantic highlighting
+    #[must_use]
+    pub const fn highlight_category(&self) -> &'static str {
+        match self {
+            Self::Number(..) => "number",
+            Self::Identifier(..) => "identifier",
+            Self::Equals
+            | Self::OpenParen
+            | Self::OpenFuncParen
+            | Self::CloseParen
+            | Self::OpenBracket
+            | Self::CloseBracket
+            | Self::Comma
+            | Self::Colon
+            | Self::Dot
+            | Self::BinaryOperator(..)
+            | Self::IOperator(..)
+            | Self::Increment
+            | Self::Decrement
+            | Self::EqOperator(..) => "operator",
+            _ => "keyword",
+        }
+    }
+
+    fn json_value(&self) -> String {
+        let value = match self {
+            Self::Number(n) => return n.to_string(),
+            Self::Identifier(name) => name.as_str(),
+            Self::Equals => "=",
+            Self::OpenParen | Self::OpenFuncParen => "(",
+            Self::CloseParen => ")",
+            Self::OpenBracket => "[",
+            Self::CloseBracket => "]",
+            Self::Comma => ",",
+            Self::Colon => ":",
+            Self::Dot => ".",
+            Self::BinaryOperator(op) => operator_symbol(*op),
+            Self::IOperator(op) => return format!("\"{}=\"", operator_symbol(*op)),
+            Self::Increment => "++",
+            Self::Decrement => "--",
+            Self::EqOperator(op) => match op {
+                EqOp::EqualTo => "==",
+                EqOp::NotEqual => "!=",
+                EqOp::Greater => ">",
+                EqOp::GreaterEq => ">=",
+                EqOp::Less => "<",
+                EqOp::LessEq => "<=",
+            },
+            Self::Inline => "inline",
+            Self::If => "if",
+            Self::Elif => "elif",
+            Self::Match => "match",
+            Self::Case => "case",
+            Self::Else => "else",
+            Self::End => "end",
+            Self::Forever => "forever",
+            Self::While => "while",
+            Self::Do => "do",
+            Self::Repeat => "repeat",
+            Self::Times => "times",
+            Self::As => "as",
+            Self::Pass => "pass",
+            Self::Break => "break",
+            Self::Continue => "continue",
+            Self::Stop => "stop",
+            Self::And => "and",
+            Self::Or => "or",
+            Self::Fn => "fn",
+            Self::Debug => "debug",
+            Self::Trace => "trace",
+            Self::Assert => "assert",
+            Self::Use => "use",
+            Self::Var => "var",
+            Self::Eof => "",
+        };
+        format!("\"{value}\"")
+    }
+}
+
+const fn operator_symbol(op: Operator) -> &'static str {
+    match op {
+        Operator::Plus => "+",
+        Operator::Minus => "-",
+        Operator::Mult => "*",
+        Operator::Pow => "**",
+        Operator::And => "&",
+        Operator::Or => "|",
+        Operator::Xor => "^",
+    }
+}
+
+/// Serializes tokens as JSON for editor semantic highlighting
+///
+/// Produces `[{"type", "value", "start", "end"}]`, where `start` and `end` are
+/// inclusive `{"line", "column"}` pairs (lines 0-based, columns 1-based).
+/// The trailing `Eof` token is left out.
+#[must_use]
+pub fn tokens_to_json(tokens: &[Token]) -> String {
+    let entries: Vec<_> = tokens
+        .iter()
+        .filter(|token| token.typ != TokenType::Eof)
+        .map(|token| {
+            let Range(start, end) = token.location;
+            format!(
+                "{{\"type\":\"{}\",\"value\":{},\"start\":{{\"line\":{},\"column\":{}}},\"end\":{{\"line\":{},\"column\":{}}}}}",
+                token.typ.highlight_category(),
+                token.typ.json_value(),
+                start.0,
+                start.1,
+                end.0,
+                end.1
+            )
+        })
+        .collect();
+    format!("[{}]", entries.join(","))
+}
+
+/// How many blocks are still waiting for their `end` (or their `while`, for
+/// a `do`), the REPL keeps reading lines until this is 0
+///
+/// A `while` right after a `do` can't close it, the block would be empty,
+/// so it is a nested loop even before its `end` is typed
+#[must_use]
+pub fn open_blocks(tokens: &[Token]) -> usize {
+    // true for a `do`, which is closed by `while` instead of `end`
+    let mut blocks = vec![];
+    for (index, token) in tokens.iter().enumerate() {
+        match token.typ {
+            TokenType::Do => blocks.push(true),
+            TokenType::While
+                if blocks.last() == Some(&true)
+                    && tokens[index - 1].typ != TokenType::Do
+                    && !while_opens_loop(tokens, index) =>
+            {
+                blocks.pop();
+            }
+            TokenType::End => {
+                blocks.pop();
+            }
+            _ if opens_block(tokens, index) => blocks.push(false),
+            _ => {}
+        }
+    }
+    blocks.len()
+}
+
+/// Whether the `while` at `index`, directly inside a `do`, starts a loop of
+/// its own instead of closing the `do`
+///
+/// It does if an `end` closes it and the `do` still gets its `while` after
+/// that `end`
+#[must_use]
+pub fn while_opens_loop(tokens: &[Token], index: usize) -> bool {
+    block_close(tokens, index + 1).is_some_and(|after| do_close(tokens, after).is_some())
+}
+
+/// whether the token at `index` starts a block closed by `end`,
+/// `else if` on one line is a branch of the open `if`
+fn opens_block(tokens: &[Token], index: usize) -> bool {
+    match tokens[index].typ {
+        TokenType::If => !index.checked_sub(1).is_some_and(|before| {
+            tokens[before].typ == TokenType::Else
+                && tokens[before].location.1 .0 == tokens[index].location.0 .0
+        }),
+        TokenType::Match
+        | TokenType::Forever
+        | TokenType::While
+        | TokenType::Repeat
+        | TokenType::Fn => true,
+        _ => false,
+    }
+}
+
+/// the index after the `end` of a block whose body starts at `from`
+fn block_close(tokens: &[Token], mut from: usize) -> Option<usize> {
+    loop {
+        from = match tokens.get(from)?.typ {
+            TokenType::End => return Some(from + 1),
+            TokenType::Eof => return None,
+            TokenType::Do => do_close(tokens, from + 1)?,
+            _ if opens_block(tokens, from) => block_close(tokens, from + 1)?,
+            _ => from + 1,
+        };
+    }
+}
+
+/// the index after the `while` closing a `do` whose body continues at `from`
+fn do_close(tokens: &[Token], mut from: usize) -> Option<usize> {
+    loop {
+        from = match tokens.get(from)?.typ {
+            TokenType::While if while_opens_loop(tokens, from) => block_close(tokens, from + 1)?,
+            TokenType::While => return Some(from + 1),
+            TokenType::End | TokenType::Eof => return None,
+            TokenType::Do => do_close(tokens, from + 1)?,
+            _ if opens_block(tokens, from) => block_close(tokens, from + 1)?,
+            _ => from + 1,
+        };
+    }
+}
+
 impl Debug for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?} at {:?}", self.typ, self.location)
@@ -61,20 +281,22 @@ impl Debug for Token {
 
 enum ErrorType {
     InvalidNumber(String),
+    NumberTooLarge(String),
+    MisplacedSeparator,
+    UnterminatedComment,
     Eof,
     InvalidChar(String),
-    TabIndent,
 }
 
 impl crate::error::ErrorType for ErrorType {
     fn get_message(&self) -> String {
         match self {
             Self::InvalidNumber(n) => format!("Invalid number: {n}"),
+            Self::NumberTooLarge(n) => format!("number {n} doesn't fit in 16 bits"),
+            Self::MisplacedSeparator => "'_' can only be used between digits".to_string(),
+            Self::UnterminatedComment => "Block comment is never closed with ']#'".to_string(),
             Self::Eof => "Unexpected End of file".to_string(),
             Self::InvalidChar(c) => format!("Invalid character: {c}"),
-            Self::TabIndent => {
-                "Pleas only format using spaces, tabs break the formatting".to_string()
-            }
         }
     }
 }
@@ -85,13 +307,27 @@ fn keyword(string: String) -> TokenType {
         "if" => TokenType::If,
         "elif" | "elseif" => TokenType::Elif,
         "else" => TokenType::Else,
+        "match" => TokenType::Match,
+        "case" => TokenType::Case,
         "end" => TokenType::End,
         "forever" => TokenType::Forever,
         "while" => TokenType::While,
+        "do" => TokenType::Do,
+        "repeat" => TokenType::Repeat,
+        "times" => TokenType::Times,
+        "as" => TokenType::As,
         "pass" => TokenType::Pass,
+        "break" => TokenType::Break,
+        "continue" => TokenType::Continue,
+        "stop" | "halt" => TokenType::Stop,
+        "and" => TokenType::And,
+        "or" => TokenType::Or,
+        "fn" => TokenType::Fn,
         "use" => TokenType::Use,
         "var" => TokenType::Var,
         "debug" => TokenType::Debug,
+        "trace" => TokenType::Trace,
+        "assert" => TokenType::Assert,
         _ => TokenType::Identifier(string),
     }
 }
@@ -136,6 +372,20 @@ pub fn tokenize(source_code: &str) -> Result<Vec<Token>, Error> {
                 T::from_char(Tt::OpenFuncParen, current_location)
             }),
             ')' => tokens.push(T::from_char(Tt::CloseParen, current_location)),
+            '[' => tokens.push(T::from_char(Tt::OpenBracket, current_location)),
+            ']' => tokens.push(T::from_char(Tt::CloseBracket, current_location)),
+            '+' if src.peek() == Some(&'+') => {
+                tokens.push(T::with_len(Tt::Increment, current_location, 2));
+                next(&mut src, &mut current_location);
+            }
+            '*' if src.peek() == Some(&'*') => {
+                tokens.push(T::with_len(
+                    Tt::BinaryOperator(Operator::Pow),
+                    current_location,
+                    2,
+                ));
+                next(&mut src, &mut current_location);
+            }
             '+' | '*' | '&' | '|' | '^' => {
                 let equals_after = matches!(src.peek(), Some('='));
 
@@ -153,6 +403,7 @@ pub fn tokenize(source_code: &str) -> Result<Vec<Token>, Error> {
             }
             '-' => tokens.push(read_hyphen(&mut src, &mut current_location)?),
             ',' => tokens.push(T::from_char(Tt::Comma, current_location)),
+            ':' => tokens.push(T::from_char(Tt::Colon, current_location)),
             '.' => tokens.push(T::from_char(Tt::Dot, current_location)),
 
             '=' => match src.peek() {
@@ -177,12 +428,14 @@ pub fn tokenize(source_code: &str) -> Result<Vec<Token>, Error> {
                     next(&mut src, &mut current_location);
                 }
             }
+            '#' if src.peek() == Some(&'[') => {
+                skip_block_comment(&mut src, &mut current_location)?;
+            }
             '#' => while !matches!(next(&mut src, &mut current_location), Some('\n') | None) {},
-            '\t' => return err!(TabIndent, Range(current_location, current_location)),
             _ => {
                 if char.is_ascii_digit() {
                     let start = current_location;
-                    let num = read_num(char, &mut src, &mut current_location)?;
+                    let num = read_num(char, &mut src, &mut current_location, false)?;
 
                     tokens.push(T {
                         typ: Tt::Number(num),
@@ -209,11 +462,36 @@ pub fn tokenize(source_code: &str) -> Result<Vec<Token>, Error> {
     Ok(tokens)
 }
 
+/// skips a `#[ ... ]#` comment, which can contain nested ones
+fn skip_block_comment(
+    src: &mut Peekable<std::str::Chars<'_>>,
+    current_location: &mut Location,
+) -> Result<(), Error> {
+    let start = *current_location;
+    next(src, current_location);
+    let mut depth = 1;
+    while depth > 0 {
+        match next(src, current_location) {
+            Some('#') if src.peek() == Some(&'[') => {
+                next(src, current_location);
+                depth += 1;
+            }
+            Some(']') if src.peek() == Some(&'#') => {
+                next(src, current_location);
+                depth -= 1;
+            }
+            Some(_) => {}
+            None => return err!(UnterminatedComment, Range(start, start)),
+        }
+    }
+    Ok(())
+}
+
 fn read_hyphen(
     src: &mut Peekable<std::str::Chars<'_>>,
     current_location: &mut Location,
 ) -> Result<Token, Error> {
-    Ok(match src.peek() {
+    Ok(match src.peek().copied() {
         None => T::from_char(Tt::BinaryOperator(Operator::Minus), *current_location),
         Some(c) => match c {
             '=' => {
@@ -221,15 +499,22 @@ fn read_hyphen(
                 next(src, current_location);
                 t
             }
+            // `a--1` is `a - -1`
+            '-' if !src.clone().nth(1).is_some_and(|c| c.is_ascii_digit()) => {
+                let t = T::with_len(Tt::Decrement, *current_location, 2);
+                next(src, current_location);
+                t
+            }
             '0'..='9' => {
                 let start = *current_location;
-                let num = -read_num(
+                let num = read_num(
                     next(src, current_location).ok_or(<Result<i16, Error>>::unwrap_err(err!(
                         Eof,
                         Range(start, *current_location)
                     )))?,
                     src,
                     current_location,
+                    true,
                 )?;
                 T {
                     typ: Tt::Number(num),
@@ -241,36 +526,71 @@ fn read_hyphen(
     })
 }
 
+/// a negative decimal can go down to -32768, a negative bit pattern
+/// like `-0x8000` is negated with wrapping
 fn read_num(
     first: char,
     src: &mut Peekable<std::str::Chars<'_>>,
     current_location: &mut Location,
+    negative: bool,
 ) -> Result<i16, Error> {
-    let mut c = src.peek();
-
+    let start = *current_location;
     if first == '0' {
-        match c {
-            Some('b') => return read_n_num(src, current_location, 2),
-            Some('x') => return read_n_num(src, current_location, 16),
-            _ => {}
+        let radix = match src.peek() {
+            Some('b') => Some(2),
+            Some('x') => Some(16),
+            Some('o') => Some(8),
+            _ => None,
+        };
+        if let Some(radix) = radix {
+            let num = read_n_num(src, current_location, radix)?;
+            return Ok(if negative { num.wrapping_neg() } else { num });
         }
     }
 
     let mut num = String::new();
+    if negative {
+        num.push('-');
+    }
     num.push(first);
+    read_digits(&mut num, src, current_location, char::is_ascii_digit)?;
+    num.parse().map_or_else(
+        |_| {
+            err!(
+                ErrorType::NumberTooLarge(num),
+                Range(start, *current_location)
+            )
+        },
+        Ok,
+    )
+}
 
-    loop {
-        let Some(n) = c else {
-            break;
-        };
-        if !n.is_ascii_digit() {
+/// reads digits into `num`, skipping single underscores between them
+fn read_digits(
+    num: &mut String,
+    src: &mut Peekable<std::str::Chars<'_>>,
+    current_location: &mut Location,
+    is_digit: fn(&char) -> bool,
+) -> Result<(), Error> {
+    while let Some(&n) = src.peek() {
+        if n == '_' {
+            let start = *current_location;
+            next(src, current_location);
+            if num.is_empty() || !src.peek().is_some_and(is_digit) {
+                return err!(
+                    ErrorType::MisplacedSeparator,
+                    Range(start, *current_location)
+                );
+            }
+            continue;
+        }
+        if !is_digit(&n) {
             break;
         }
-        num.push(*n);
+        num.push(n);
         next(src, current_location);
-        c = src.peek();
     }
-    Ok(num.parse().unwrap())
+    Ok(())
 }
 
 fn read_identifier(
@@ -284,10 +604,7 @@ fn read_identifier(
     identifier.push(char);
     let mut c = src.peek();
 
-    loop {
-        let Some(a) = c else {
-            break;
-        };
+    while let Some(a) = c {
         if !a.is_alphanumeric() && *a != '_' {
             break;
         }
@@ -305,27 +622,18 @@ fn read_n_num(
     radix: u32,
 ) -> Result<i16, Error> {
     let start = *current_location;
-    next(src, current_location);
-    let mut c = src.peek();
+    let prefix = next(src, current_location).unwrap_or_default();
     let mut num = String::new();
-
-    loop {
-        let Some(n) = c else {
-            break;
-        };
-        if !n.is_ascii_hexdigit() {
-            break;
-        }
-        num.push(*n);
-        next(src, current_location);
-        c = src.peek();
-    }
+    read_digits(&mut num, src, current_location, char::is_ascii_hexdigit)?;
     u16::from_str_radix(num.as_str(), radix).map_or_else(
-        |_| {
-            err!(
-                ErrorType::InvalidNumber(num),
-                Range(start, *current_location)
-            )
+        |error| {
+            let literal = format!("0{prefix}{num}");
+            let location = Range(start, *current_location);
+            if *error.kind() == IntErrorKind::PosOverflow {
+                err!(ErrorType::NumberTooLarge(literal), location)
+            } else {
+                err!(ErrorType::InvalidNumber(literal), location)
+            }
         },
         |u| Ok(u as i16),
     )
@@ -12,7 +12,9 @@ impl Debug for Location {
 }
 
 /// [from, to], both inclusive
-#[derive(Clone, Copy, PartialEq, Eq)]
+///
+/// ordered by start, then by end
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Range(pub Location, pub Location);
 
 impl Range {
@@ -20,12 +22,32 @@ impl Range {
     pub const fn single_char(location: Location) -> Self {
         Self(location, location)
     }
+
+    #[must_use]
+    pub fn contains(self, location: Location) -> bool {
+        self.0 <= location && location <= self.1
+    }
+
+    /// whether the ranges share at least one location
+    #[must_use]
+    pub fn overlaps(self, other: Self) -> bool {
+        self.0 <= other.1 && other.0 <= self.1
+    }
+
+    fn is_ordered(self) -> bool {
+        self.0 <= self.1
+    }
 }
 
+/// the smallest range covering both
 impl Add for Range {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
+        debug_assert!(
+            self.is_ordered() && rhs.is_ordered(),
+            "adding a reversed range: {self:?} + {rhs:?}"
+        );
         Self(self.0.min(rhs.0), self.1.max(rhs.1))
     }
 }
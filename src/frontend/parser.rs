@@ -6,7 +6,10 @@ use crate::{
     frontend::{ErrorType, Range},
 };
 
-use super::{EqualityOperator, Expression, ExpressionType, Ident, Operator, Token, TokenType};
+use super::{
+    while_opens_loop, Expression, ExpressionType, Ident, LogicalOperator, Operator, Token,
+    TokenType,
+};
 
 #[derive(Default)]
 pub struct Parser {
@@ -94,6 +97,7 @@ impl Parser {
         Ok(match current.typ {
             TokenType::Inline => self.parse_inline_declaration()?,
             TokenType::If => self.parse_conditional()?,
+            TokenType::Match => self.parse_match()?,
             TokenType::Pass => {
                 let token = self.eat();
                 Expression {
@@ -101,30 +105,79 @@ impl Parser {
                     location: token.location,
                 }
             }
+            TokenType::Break => {
+                let token = self.eat();
+                Expression {
+                    typ: ExpressionType::Break,
+                    location: token.location,
+                }
+            }
+            TokenType::Continue => {
+                let token = self.eat();
+                Expression {
+                    typ: ExpressionType::Continue,
+                    location: token.location,
+                }
+            }
+            TokenType::Stop => {
+                let token = self.eat();
+                Expression {
+                    typ: ExpressionType::Stop,
+                    location: token.location,
+                }
+            }
             TokenType::Use => self.parse_use_statement()?,
             TokenType::Var => self.parse_var_declaration()?,
             TokenType::Forever => self.parse_endless()?,
             TokenType::While => self.parse_while()?,
+            TokenType::Do => self.parse_do_while()?,
+            TokenType::Repeat => self.parse_repeat()?,
+            TokenType::Fn => self.parse_function_definition()?,
+            TokenType::Trace => {
+                let start = self.eat().location;
+                let tag = self.parse_expression()?;
+                Expression {
+                    location: start + tag.location,
+                    typ: ExpressionType::Trace(Box::new(tag)),
+                }
+            }
+            TokenType::Assert => {
+                let start = self.eat().location;
+                let condition = self.parse_expression()?;
+                Expression {
+                    location: start + condition.location,
+                    typ: ExpressionType::Assert(Box::new(condition)),
+                }
+            }
             _ => self.parse_expression()?,
         })
     }
 
     fn parse_conditional(&mut self) -> Res {
         let start = self.eat().location;
-        let (condition, body) = self.parse_conditional_branch()?;
+        let mut missing = None;
+        let (condition, body) = self.parse_conditional_branch(start, &mut missing)?;
         // self.at is now elif, else or end
         let mut paths = vec![];
 
-        while matches!(self.at().typ, TokenType::Elif | TokenType::Eof) {
-            self.eat();
-            paths.push(self.parse_conditional_branch()?);
+        while matches!(self.at().typ, TokenType::Elif) || self.at_else_if() {
+            let keyword = self.eat();
+            let mut location = keyword.location;
+            if matches!(keyword.typ, TokenType::Else) {
+                location = location + self.eat().location;
+            }
+            paths.push(self.parse_conditional_branch(location, &mut missing)?);
         }
 
         let alternate = if matches!(self.at().typ, TokenType::Else) {
             Some({
                 self.eat();
                 let mut body = vec![];
-                while !matches!(self.at().typ, TokenType::End | TokenType::Eof) {
+                while !matches!(
+                    self.at().typ,
+                    TokenType::End | TokenType::Elif | TokenType::Eof
+                ) && !self.at_else_if()
+                {
                     body.push(self.parse_statement()?);
                 }
                 if body.is_empty() {
@@ -136,9 +189,24 @@ impl Parser {
             None
         };
 
+        if alternate.is_some() && (matches!(self.at().typ, TokenType::Elif) || self.at_else_if()) {
+            let mut elif = self.eat().location;
+            if matches!(self.at().typ, TokenType::If) {
+                elif = elif + self.eat().location;
+            }
+            // parse the rest of the conditional so the error doesn't cascade
+            self.parse_conditional_branch(elif, &mut missing)?;
+            self.eat_if_or(match_fn!(TokenType::End), ErrorType::MissingEnd, start)?;
+            return err!(ElifAfterElse, elif);
+        }
+
         let end = self
             .eat_if_or(match_fn!(TokenType::End), ErrorType::MissingEnd, start)?
             .location;
+        // reported after the whole conditional is parsed so the error doesn't cascade
+        if let Some(keyword) = missing {
+            return err!(MissingCondition, keyword);
+        }
         Ok(Expression {
             typ: ExpressionType::Conditional {
                 condition: Box::new(condition),
@@ -150,8 +218,41 @@ impl Parser {
         })
     }
 
-    fn parse_conditional_branch(&mut self) -> Res<(Expression, Vec<Expression>)> {
-        let condition = self.parse_expression()?;
+    /// the condition has to start on the line of its keyword, otherwise the
+    /// body would be parsed as the condition. A missing one is put into
+    /// `missing` and replaced by a `pass`, so the caller can finish parsing
+    fn parse_condition(&mut self, keyword: Range, missing: &mut Option<Range>) -> Res {
+        let at = self.at();
+        if matches!(
+            at.typ,
+            TokenType::End | TokenType::Elif | TokenType::Else | TokenType::Eof
+        ) || at.location.0 .0 != keyword.1 .0
+        {
+            missing.get_or_insert(keyword);
+            return Ok(Expression {
+                typ: ExpressionType::Pass,
+                location: keyword,
+            });
+        }
+        self.parse_expression()
+    }
+
+    /// `else if` on one line is another branch like `elif`, an `if` on the
+    /// next line is a nested conditional inside the `else`
+    fn at_else_if(&self) -> bool {
+        let at = self.at();
+        matches!(at.typ, TokenType::Else)
+            && self.tokens.get(1).is_some_and(|next| {
+                matches!(next.typ, TokenType::If) && next.location.0 .0 == at.location.1 .0
+            })
+    }
+
+    fn parse_conditional_branch(
+        &mut self,
+        keyword: Range,
+        missing: &mut Option<Range>,
+    ) -> Res<(Expression, Vec<Expression>)> {
+        let condition = self.parse_condition(keyword, missing)?;
         let start = self.at().location;
         let mut body = vec![];
         while !matches!(
@@ -160,12 +261,71 @@ impl Parser {
         ) {
             body.push(self.parse_statement()?);
         }
-        if body.is_empty() {
+        if body.is_empty() && missing.is_none() {
             return err!(EmptyBlock, start + self.at().location);
         }
         Ok((condition, body))
     }
 
+    fn parse_match(&mut self) -> Res {
+        use TokenType as T;
+        let start = self.eat().location;
+        let value = self.parse_expression()?;
+        if !matches!(self.at().typ, T::Case) {
+            return err!(MissingCase, self.at().location);
+        }
+
+        let mut arms = vec![];
+        let mut missing_colon = None;
+        while matches!(self.at().typ, T::Case) {
+            self.eat();
+            let case = self.parse_expression()?;
+            if matches!(self.at().typ, T::Colon) {
+                self.eat();
+            } else {
+                missing_colon.get_or_insert(case.location);
+            }
+            let body = self.parse_arm(start)?;
+            arms.push((case, body));
+        }
+
+        let alternate = if matches!(self.at().typ, T::Else) {
+            self.eat();
+            Some(self.parse_arm(start)?)
+        } else {
+            None
+        };
+
+        let end = self
+            .eat_if_or(match_fn!(T::End), ErrorType::MissingEnd, start)?
+            .location;
+        // reported after the whole match is parsed so the error doesn't cascade
+        if let Some(location) = missing_colon {
+            return err!(MissingColon, location);
+        }
+        Ok(Expression {
+            typ: ExpressionType::Match {
+                value: Box::new(value),
+                arms,
+                alternate,
+            },
+            location: start + end,
+        })
+    }
+
+    /// the body of a `case` or the `else` of a `match`
+    fn parse_arm(&mut self, start: Range) -> Res<Vec<Expression>> {
+        use TokenType as T;
+        let mut body = vec![];
+        while !matches!(self.at().typ, T::Case | T::Else | T::End | T::Eof) {
+            body.push(self.parse_statement()?);
+        }
+        if body.is_empty() {
+            return err!(EmptyBlock, start + self.at().location);
+        }
+        Ok(body)
+    }
+
     fn parse_endless(&mut self) -> Res {
         use TokenType as T;
         let start = self.eat().location;
@@ -188,12 +348,16 @@ impl Parser {
     fn parse_while(&mut self) -> Res {
         use TokenType as T;
         let start = self.eat().location;
-        let condition = self.parse_expression()?;
+        let mut missing = None;
+        let condition = self.parse_condition(start, &mut missing)?;
         let mut body = vec![];
         while !matches!(self.at().typ, T::End | T::Eof) {
             body.push(self.parse_statement()?);
         }
         let end = self.eat_if_or(match_fn!(T::End), ErrorType::MissingEnd, start)?;
+        if let Some(keyword) = missing {
+            return err!(MissingCondition, keyword);
+        }
         if body.is_empty() {
             return err!(EmptyBlock, start + self.at().location);
         }
@@ -206,6 +370,128 @@ impl Parser {
         })
     }
 
+    fn parse_do_while(&mut self) -> Res {
+        let start = self.eat().location;
+        let (body, keyword) = self.parse_do_body(start)?;
+        if body.is_empty() {
+            return err!(EmptyBlock, start + self.at().location);
+        }
+        let mut missing = None;
+        let condition = self.parse_condition(keyword.location, &mut missing)?;
+        if let Some(keyword) = missing {
+            return err!(MissingCondition, keyword);
+        }
+        Ok(Expression {
+            location: start + condition.location,
+            typ: ExpressionType::DoWhile {
+                body,
+                condition: Box::from(condition),
+            },
+        })
+    }
+
+    /// the statements of a `do` block and the `while` that closes it. A
+    /// `while` inside starts a nested loop if its `end` is followed by the
+    /// `while` of the block, see [`while_opens_loop`]
+    fn parse_do_body(&mut self, start: Range) -> Res<(Vec<Expression>, Token)> {
+        use TokenType as T;
+        let mut body = vec![];
+        loop {
+            let nested =
+                self.at().typ == T::While && while_opens_loop(self.tokens.make_contiguous(), 0);
+            if !nested && matches!(self.at().typ, T::While | T::Eof) {
+                break;
+            }
+            body.push(self.parse_statement()?);
+        }
+        let keyword = self.eat_if_or(match_fn!(T::While), ErrorType::MissingWhile, start)?;
+        Ok((body, keyword))
+    }
+
+    fn parse_repeat(&mut self) -> Res {
+        use TokenType as T;
+        let start = self.eat().location;
+        let count = self.parse_expression()?;
+        self.eat_if(match_fn!(T::Times), ErrorType::MissingTimes)?;
+        let index = if self.at().typ == T::As {
+            self.eat();
+            let token = self.eat();
+            let T::Identifier(symbol) = token.typ else {
+                return err!(InvalidDeclartion, token.location);
+            };
+            Some(Ident {
+                symbol,
+                location: token.location,
+            })
+        } else {
+            None
+        };
+        let mut body = vec![];
+        while !matches!(self.at().typ, T::End | T::Eof) {
+            body.push(self.parse_statement()?);
+        }
+        let end = self.eat_if_or(match_fn!(T::End), ErrorType::MissingEnd, start)?;
+        if body.is_empty() {
+            return err!(EmptyBlock, start + self.at().location);
+        }
+        Ok(Expression {
+            typ: ExpressionType::Repeat {
+                count: Box::from(count),
+                index,
+                body,
+            },
+            location: start + end.location,
+        })
+    }
+
+    fn parse_function_definition(&mut self) -> Res {
+        use TokenType as T;
+        let start = self.eat().location;
+        let token = self.eat();
+        let T::Identifier(symbol) = token.typ else {
+            return err!(InvalidDeclartion, token.location);
+        };
+        let (args, _) = self.parse_args()?;
+        let params = args
+            .into_iter()
+            .map(|arg| match arg.typ {
+                ExpressionType::Identifier(symbol) => Ok(Ident {
+                    symbol,
+                    location: arg.location,
+                }),
+                _ => err!(InvalidDeclartion, arg.location),
+            })
+            .collect::<Res<Vec<_>, _>>();
+        let mut body = vec![];
+        while !matches!(self.at().typ, T::End | T::Eof) {
+            body.push(self.parse_statement()?);
+        }
+        let end = self.eat_if_or(match_fn!(T::End), ErrorType::MissingEnd, start)?;
+        // checked after the body, so parsing resumes after the `end`
+        let params = params?;
+        let duplicate = params
+            .iter()
+            .enumerate()
+            .find(|(index, param)| params[..*index].iter().any(|p| p.symbol == param.symbol));
+        if let Some((_, param)) = duplicate {
+            return err!(DuplicateParam, param.location);
+        }
+        if body.is_empty() {
+            return err!(EmptyBlock, start + self.at().location);
+        }
+        Ok(Expression {
+            typ: ExpressionType::FunctionDefinition {
+                ident: Ident {
+                    symbol,
+                    location: token.location,
+                },
+                params,
+                body,
+            },
+            location: start + end.location,
+        })
+    }
+
     fn parse_use_statement(&mut self) -> Res {
         use TokenType as T;
         let start = self.eat().location;
@@ -226,11 +512,30 @@ impl Parser {
                     location: token.location,
                 }),
                 _ => return err!(InvalidModuleName, token.location),
-            };
+            }
         }
+        let alias = if self.at().typ == T::As {
+            self.eat();
+            let token = self.eat();
+            let T::Identifier(symbol) = token.typ else {
+                return err!(InvalidDeclartion, token.location);
+            };
+            if imports.len() > 1 {
+                return err!(MultipleAliased, token.location);
+            }
+            Some(Ident {
+                symbol,
+                location: token.location,
+            })
+        } else {
+            None
+        };
         Ok(Expression {
-            location: start + imports.last().location,
-            typ: ExpressionType::Use(imports),
+            location: start + alias.as_ref().unwrap_or_else(|| imports.last()).location,
+            typ: ExpressionType::Use {
+                modules: imports,
+                alias,
+            },
         })
     }
 
@@ -239,6 +544,23 @@ impl Parser {
         let start = self.eat().location;
         let token = self.eat();
         match token.typ {
+            T::Identifier(symbol) if matches!(self.at().typ, T::OpenBracket) => {
+                self.eat();
+                let size = self.parse_expression()?;
+                let end = self
+                    .eat_if(match_fn!(T::CloseBracket), ErrorType::MissingClosingBracket)?
+                    .location;
+                Ok(Expression {
+                    typ: ExpressionType::ArrayDeclaration {
+                        ident: Ident {
+                            symbol,
+                            location: token.location,
+                        },
+                        size: Box::new(size),
+                    },
+                    location: start + end,
+                })
+            }
             T::Identifier(symbol) => Ok(Expression {
                 typ: ExpressionType::VarDeclaration {
                     ident: Ident {
@@ -283,6 +605,19 @@ impl Parser {
         let left = self.parse_i_assignment()?;
 
         if matches!(self.at().typ, TokenType::Equals) {
+            if let ExpressionType::Index { array, index } = left.typ {
+                self.eat();
+                let value = self.parse_assignment()?;
+                let location = left.location + value.location;
+                return Ok(Expression {
+                    typ: ExpressionType::IndexAssignment {
+                        array,
+                        index,
+                        value: Box::new(value),
+                    },
+                    location,
+                });
+            }
             let ExpressionType::Identifier(name) = left.typ else {
                 return err!(InvalidAssignment, self.at().location);
             };
@@ -305,7 +640,41 @@ impl Parser {
     }
 
     fn parse_i_assignment(&mut self) -> Res {
-        let left = self.parse_eq_expression()?;
+        let left = self.parse_or_expression()?;
+
+        if let TokenType::Increment | TokenType::Decrement = self.at().typ {
+            let ExpressionType::Identifier(ref name) = left.typ else {
+                return err!(IncrementInExpression, left.location + self.eat().location);
+            };
+            let token = self.eat();
+            if matches!(
+                self.at().typ,
+                TokenType::BinaryOperator(..)
+                    | TokenType::EqOperator(..)
+                    | TokenType::And
+                    | TokenType::Or
+            ) {
+                // skip the rest of the expression, it's part of this error
+                self.eat();
+                self.parse_or_expression()?;
+                return err!(IncrementInExpression, left.location + token.location);
+            }
+            let operator = if token.typ == TokenType::Increment {
+                Operator::Plus
+            } else {
+                Operator::Minus
+            };
+            return Ok(Expression {
+                typ: ExpressionType::Increment {
+                    ident: Ident {
+                        symbol: name.clone(),
+                        location: left.location,
+                    },
+                    operator,
+                },
+                location: left.location + token.location,
+            });
+        }
 
         if let TokenType::IOperator(operator) = self.at().typ {
             let ExpressionType::Identifier(ref name) = left.typ else {
@@ -330,28 +699,38 @@ impl Parser {
         Ok(left)
     }
 
-    fn parse_eq_expression(&mut self) -> Res {
-        let mut left = self.parse_additive()?;
+    fn parse_or_expression(&mut self) -> Res {
+        let mut left = self.parse_and_expression()?;
+
+        while matches!(self.at().typ, TokenType::Or) {
+            self.eat();
+            let right = self.parse_and_expression()?;
+            let location = left.location + right.location;
+            left = Expression {
+                typ: ExpressionType::LogicalExpr {
+                    left: Box::from(left),
+                    right: Box::from(right),
+                    operator: LogicalOperator::Or,
+                },
+                location,
+            };
+        }
 
-        let mut operator = EqualityOperator::EqualTo; // default, gets overwritten
+        Ok(left)
+    }
 
-        while {
-            match self.at().typ {
-                TokenType::EqOperator(op) => {
-                    operator = op;
-                    true
-                }
-                _ => false,
-            }
-        } {
+    fn parse_and_expression(&mut self) -> Res {
+        let mut left = self.parse_eq_expression()?;
+
+        while matches!(self.at().typ, TokenType::And) {
             self.eat();
-            let right = self.parse_additive()?;
+            let right = self.parse_eq_expression()?;
             let location = left.location + right.location;
             left = Expression {
-                typ: ExpressionType::EqExpr {
+                typ: ExpressionType::LogicalExpr {
                     left: Box::from(left),
                     right: Box::from(right),
-                    operator,
+                    operator: LogicalOperator::And,
                 },
                 location,
             };
@@ -360,6 +739,37 @@ impl Parser {
         Ok(left)
     }
 
+    /// comparisons don't chain, `a < b < c` would compare a boolean to `c`
+    fn parse_eq_expression(&mut self) -> Res {
+        let left = self.parse_additive()?;
+
+        let TokenType::EqOperator(operator) = self.at().typ else {
+            return Ok(left);
+        };
+        self.eat();
+        let right = self.parse_additive()?;
+
+        if matches!(self.at().typ, TokenType::EqOperator(..)) {
+            let chained = self.at().location;
+            // skip the rest of the chain so it isn't reported again
+            while matches!(self.at().typ, TokenType::EqOperator(..)) {
+                self.eat();
+                self.parse_additive()?;
+            }
+            return err!(ChainedComparison, chained);
+        }
+
+        let location = left.location + right.location;
+        Ok(Expression {
+            typ: ExpressionType::EqExpr {
+                left: Box::from(left),
+                right: Box::from(right),
+                operator,
+            },
+            location,
+        })
+    }
+
     fn parse_additive(&mut self) -> Res {
         let mut left = self.parse_multiplicative()?;
 
@@ -391,7 +801,7 @@ impl Parser {
     }
 
     fn parse_multiplicative(&mut self) -> Res {
-        let mut left = self.parse_call_member()?;
+        let mut left = self.parse_power()?;
 
         let mut operator = Operator::Plus; // default, gets overwritten
 
@@ -405,7 +815,7 @@ impl Parser {
             }
         } {
             self.eat();
-            let right = self.parse_call_member()?;
+            let right = self.parse_power()?;
             let location = left.location + right.location;
             left = Expression {
                 typ: ExpressionType::BinaryExpr {
@@ -420,17 +830,70 @@ impl Parser {
         Ok(left)
     }
 
+    /// right associative, `a ** b ** c` is `a ** (b ** c)`
+    fn parse_power(&mut self) -> Res {
+        let left = self.parse_call_member()?;
+
+        if !matches!(self.at().typ, TokenType::BinaryOperator(Operator::Pow)) {
+            return Ok(left);
+        }
+        self.eat();
+        let right = self.parse_power()?;
+        let location = left.location + right.location;
+        Ok(Expression {
+            typ: ExpressionType::BinaryExpr {
+                left: Box::from(left),
+                right: Box::from(right),
+                operator: Operator::Pow,
+            },
+            location,
+        })
+    }
+
     fn parse_call_member(&mut self) -> Res {
         let member = self.parse_member()?;
 
         if matches!(self.at().typ, TokenType::OpenFuncParen) {
             return self.parse_call(member);
         }
+        if matches!(self.at().typ, TokenType::OpenBracket) {
+            return self.parse_index(member);
+        }
         Ok(member)
     }
 
+    fn parse_index(&mut self, array: Expression) -> Res {
+        self.eat();
+        let index = self.parse_expression()?;
+        let end = self
+            .eat_if(
+                match_fn!(TokenType::CloseBracket),
+                ErrorType::MissingClosingBracket,
+            )?
+            .location;
+        let ExpressionType::Identifier(symbol) = array.typ else {
+            return err!(InvalidIndex, array.location);
+        };
+        Ok(Expression {
+            typ: ExpressionType::Index {
+                array: Ident {
+                    symbol,
+                    location: array.location,
+                },
+                index: Box::new(index),
+            },
+            location: array.location + end,
+        })
+    }
+
     fn parse_call(&mut self, caller: Expression) -> Res {
         let (args, end) = self.parse_args()?;
+        if !matches!(
+            caller.typ,
+            ExpressionType::Identifier(..) | ExpressionType::Member { .. }
+        ) {
+            return err!(InvalidCall, caller.location);
+        }
 
         if matches!(self.at().typ, TokenType::OpenFuncParen) {
             return err!(FunctionChaining, self.at().location);
@@ -485,6 +948,11 @@ impl Parser {
 
         while matches!(self.at().typ, TokenType::Dot) {
             let dot = self.eat().location;
+            match object.typ {
+                ExpressionType::Identifier(..) => {}
+                ExpressionType::Member { .. } => return err!(NestedMember, dot),
+                _ => return err!(InvalidMethodCall, object.location),
+            }
             let property = self.parse_primary()?;
 
             let ExpressionType::Identifier(name) = property.typ else {
@@ -519,15 +987,44 @@ impl Parser {
                 typ: ExpressionType::NumericLiteral(value),
                 location: token.location,
             },
-            TokenType::Debug => Expression {
-                typ: ExpressionType::Debug,
-                location: token.location,
-            },
+            TokenType::Debug => {
+                if !matches!(self.at().typ, TokenType::OpenFuncParen) {
+                    return Ok(Expression {
+                        typ: ExpressionType::Debug(None),
+                        location: token.location,
+                    });
+                }
+                self.eat();
+                let value = self.parse_expression()?;
+                let end = self.eat_if(
+                    match_fn!(TokenType::CloseParen),
+                    ErrorType::MissingClosingParen,
+                )?;
+                Expression {
+                    typ: ExpressionType::Debug(Some(Box::new(value))),
+                    location: token.location + end.location,
+                }
+            }
             TokenType::OpenParen => {
                 let value = self.parse_expression()?;
                 self.eat_if(match_fn!(TokenType::CloseParen), ErrorType::ExpectedParen)?;
                 value
             }
+            TokenType::OpenBracket => {
+                let elements = if matches!(self.at().typ, TokenType::CloseBracket) {
+                    vec![]
+                } else {
+                    self.parse_arguments_list()?
+                };
+                let end = self.eat_if(
+                    match_fn!(TokenType::CloseBracket),
+                    ErrorType::MissingClosingBracket,
+                )?;
+                Expression {
+                    typ: ExpressionType::ArrayLiteral(elements),
+                    location: token.location + end.location,
+                }
+            }
             TokenType::Eof => return err!(Eof, token.location),
             _ => return err!(UnexpectedOther, token.location),
         })
@@ -1,5 +1,19 @@
 pub mod backend;
-mod error;
+pub mod error;
 pub mod frontend;
+pub mod warning;
 
-pub use error::Error;
+pub use error::{Error, RED};
+pub use warning::Warning;
+
+/// Tokenizes, parses and compiles `source`, with the options of its pragma line
+///
+/// # Errors
+///
+/// on any error in the pragma, the tokenizer, the parser or the compiler
+pub fn compile_source(source: &str) -> Result<Vec<backend::Instruction>, Vec<Error>> {
+    let options = backend::Options::from_pragma(source).map_err(|err| vec![err])?;
+    let tokens = frontend::tokenize(source).map_err(|err| vec![err])?;
+    let ast = frontend::Parser::new().produce_ast(tokens)?;
+    Ok(backend::compile_with_options(ast, options)?.0)
+}
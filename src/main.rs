@@ -1,17 +1,31 @@
 use std::{
     collections::VecDeque,
     env,
+    fmt::{Display, Write as _},
     fs::{self, create_dir_all, File},
-    io::{self, Read, Write},
+    io::{self, IsTerminal, Read, Write},
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
 };
 
 use colored::{Colorize, CustomColor};
-use redstone_compiler::frontend::{tokenize, Parser};
+use redstone_compiler::error::TAB_WIDTH;
+use redstone_compiler::frontend::{open_blocks, tokenize, tokens_to_json, Parser, Range};
+use redstone_compiler::warning::{Category, Warning};
+use redstone_compiler::{Error, RED};
 
-use redstone_compiler::backend::compile_program;
+use redstone_compiler::backend::{
+    compile_with_details, compile_with_warnings, parse_asm, parse_bin, to_intel_hex, Instruction,
+    Options, Pass, Symbols,
+};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// how often `--watch` checks the source file for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(300);
+
 const fn color_from_hex(n: i32) -> CustomColor {
     CustomColor {
         r: (n >> 16 & 0xff) as u8,
@@ -31,13 +45,44 @@ const REDSTONE: [CustomColor; 8] = [
     color_from_hex(0x0069_1200),
 ];
 
+/// set by `--emit=stdout`, which keeps stdout for the assembly
+static ASM_ON_STDOUT: AtomicBool = AtomicBool::new(false);
+
+/// prints a line of output about the compilation, to stderr if stdout is
+/// reserved for the assembly
+macro_rules! report {
+    ($($arg:tt)*) => {
+        if ASM_ON_STDOUT.load(Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
 fn redstone_color_print(str: &str) {
-    for char in str.chars() {
-        print!(
-            "{}",
+    let colored: String = str
+        .chars()
+        .map(|char| {
             char.to_string()
                 .custom_color(fastrand::choice(REDSTONE).unwrap())
-        );
+                .to_string()
+        })
+        .collect();
+    report!("{colored}");
+}
+
+/// a problem with the command line
+fn cli_error(message: impl Display) {
+    eprintln!("{} {message}", "Error:".custom_color(RED));
+}
+
+/// colors only go to a terminal, and never when `NO_COLOR` is set. Errors and
+/// warnings are printed to stderr, so that's the one that has to be a terminal
+fn configure_colors() {
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    if no_color || !io::stderr().is_terminal() {
+        colored::control::set_override(false);
     }
 }
 
@@ -50,120 +95,603 @@ fn has_arg(args: &mut VecDeque<String>, arg: &'static str) -> bool {
     }
 }
 
+/// takes a `-O<level>` flag out of the args, printing invalid levels
+fn optimize_arg(args: &mut VecDeque<String>) -> Result<Option<u8>, ()> {
+    let Some(index) = args.iter().position(|a| a.starts_with("-O")) else {
+        return Ok(None);
+    };
+    let arg = args.remove(index).unwrap_or_default();
+    match arg[2..].parse() {
+        Ok(level) if level <= Options::MAX_OPTIMIZE => Ok(Some(level)),
+        _ => {
+            cli_error(format_args!(
+                "invalid optimization level {arg}, expected -O0 to -O{}",
+                Options::MAX_OPTIMIZE
+            ));
+            Err(())
+        }
+    }
+}
+
+/// takes `--strict` (or `-Werror`) or `--werror-on=<categories>` out of the args,
+/// printing unknown category names
+fn werror_arg(args: &mut VecDeque<String>) -> Result<Option<Vec<Category>>, ()> {
+    if has_arg(args, "--strict") | has_arg(args, "-Werror") {
+        return Ok(Some(Category::ALL.to_vec()));
+    }
+    let Some(index) = args.iter().position(|a| a.starts_with("--werror-on=")) else {
+        return Ok(None);
+    };
+    let arg = args.remove(index).unwrap_or_default();
+    arg["--werror-on=".len()..]
+        .split(',')
+        .map(|name| {
+            Category::from_name(name).ok_or_else(|| {
+                cli_error(format_args!(
+                    "unknown warning category {name}, see --list-warnings"
+                ));
+            })
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+/// a file produced next to the program
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Artifact {
+    Asm,
+    Bin,
+    Loc,
+    Hex,
+}
+
+impl Artifact {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "asm" => Self::Asm,
+            "bin" => Self::Bin,
+            "loc" => Self::Loc,
+            "hex" => Self::Hex,
+            _ => return None,
+        })
+    }
+
+    const fn extension(self) -> &'static str {
+        match self {
+            Self::Asm => "asm",
+            Self::Bin => "bin",
+            Self::Loc => "loc",
+            Self::Hex => "hex",
+        }
+    }
+}
+
+/// which artifacts get produced
+struct Emit {
+    artifacts: Vec<Artifact>,
+    /// print the assembly instead of writing it to a file
+    stdout: bool,
+}
+
+/// takes `--emit=<artifacts>` out of the args, printing unknown artifacts.
+/// Without it, `.asm` and `.bin` are written, plus `.loc`/`.hex` for `--loc`/`--hex`
+fn emit_arg(args: &mut VecDeque<String>) -> Result<Emit, ()> {
+    let loc = has_arg(args, "--loc");
+    let hex = has_arg(args, "--hex");
+    let Some(index) = args.iter().position(|a| a.starts_with("--emit=")) else {
+        let mut artifacts = vec![Artifact::Asm, Artifact::Bin];
+        if loc {
+            artifacts.push(Artifact::Loc);
+        }
+        if hex {
+            artifacts.push(Artifact::Hex);
+        }
+        return Ok(Emit {
+            artifacts,
+            stdout: false,
+        });
+    };
+    let arg = args.remove(index).unwrap_or_default();
+    let mut emit = Emit {
+        artifacts: vec![],
+        stdout: false,
+    };
+    for name in arg["--emit=".len()..].split(',') {
+        if name == "stdout" {
+            emit.stdout = true;
+            continue;
+        }
+        let Some(artifact) = Artifact::from_name(name) else {
+            cli_error(format_args!(
+                "unknown artifact {name}, expected asm, bin, loc, hex or stdout"
+            ));
+            return Err(());
+        };
+        if !emit.artifacts.contains(&artifact) {
+            emit.artifacts.push(artifact);
+        }
+    }
+    Ok(emit)
+}
+
+fn list_warnings() {
+    for category in Category::ALL {
+        println!("{:<10} {}", category.name().bold(), category.description());
+    }
+}
+
+/// options from the pragma line, overridden by CLI flags
+fn compile_options(code: &str, path: &str, flags: &Flags) -> Option<Options> {
+    let mut options = match Options::from_pragma(code) {
+        Ok(options) => options,
+        Err(err) => {
+            print_error(
+                &err,
+                code,
+                path,
+                flags,
+                flags.tab_width.unwrap_or(TAB_WIDTH),
+            );
+            return None;
+        }
+    };
+    if let Some(optimize) = flags.optimize {
+        options.optimize = optimize;
+    }
+    if let Some(werror) = flags.werror.clone() {
+        options.werror = werror;
+    }
+    if let Some(tab_width) = flags.tab_width {
+        options.tab_width = tab_width;
+    }
+    Some(options)
+}
+
+/// takes `--error-format=<human|json>` out of the args,
+/// returns whether diagnostics are printed as JSON
+fn error_format_arg(args: &mut VecDeque<String>) -> Result<bool, ()> {
+    let Some(index) = args.iter().position(|a| a.starts_with("--error-format=")) else {
+        return Ok(false);
+    };
+    let arg = args.remove(index).unwrap_or_default();
+    match &arg["--error-format=".len()..] {
+        "human" => Ok(false),
+        "json" => Ok(true),
+        format => {
+            cli_error(format_args!(
+                "unknown error format {format}, expected human or json"
+            ));
+            Err(())
+        }
+    }
+}
+
+/// takes `--tab-width=<columns>` out of the args, printing invalid widths
+fn tab_width_arg(args: &mut VecDeque<String>) -> Result<Option<usize>, ()> {
+    let Some(index) = args.iter().position(|a| a.starts_with("--tab-width=")) else {
+        return Ok(None);
+    };
+    let arg = args.remove(index).unwrap_or_default();
+    match arg["--tab-width=".len()..].parse() {
+        Ok(width) if (1..=Options::MAX_TAB_WIDTH).contains(&width) => Ok(Some(width)),
+        _ => {
+            cli_error(format_args!(
+                "invalid tab width {}, expected 1 to {}",
+                &arg["--tab-width=".len()..],
+                Options::MAX_TAB_WIDTH
+            ));
+            Err(())
+        }
+    }
+}
+
+/// JSON goes to stderr, one diagnostic per line
+fn print_error(err: &Error, code: &str, path: &str, flags: &Flags, tab_width: usize) {
+    if flags.json_errors {
+        eprintln!("{}", err.to_json(path));
+    } else {
+        err.pretty_print(code, path, tab_width);
+    }
+}
+
+fn print_warning(warning: &Warning, code: &str, path: &str, flags: &Flags, tab_width: usize) {
+    if flags.json_errors {
+        eprintln!("{}", warning.to_json(path));
+    } else {
+        warning.pretty_print(code, path, tab_width);
+    }
+}
+
+/// command line switches that affect a single compilation
+#[allow(clippy::struct_excessive_bools)]
+struct Flags {
+    debug: bool,
+    emit_tokens_json: bool,
+    deadcode: bool,
+    optimize: Option<u8>,
+    werror: Option<Vec<Category>>,
+    emit: Emit,
+    cycles: bool,
+    annotate: bool,
+    symbols: bool,
+    json_errors: bool,
+    tab_width: Option<usize>,
+}
+
 fn main() -> io::Result<()> {
-    redstone_color_print(format!("RedC v{VERSION}\n").as_str());
+    configure_colors();
     let mut args: VecDeque<_> = env::args().collect();
     args.pop_front();
+    let Ok(emit) = emit_arg(&mut args) else {
+        return Ok(());
+    };
+    ASM_ON_STDOUT.store(emit.stdout, Ordering::Relaxed);
+    redstone_color_print(&format!("RedC v{VERSION}"));
 
     let debug = has_arg(&mut args, "--dbg");
+    let emit_tokens_json = has_arg(&mut args, "--emit-tokens-json");
+    let deadcode = has_arg(&mut args, "--deadcode") | has_arg(&mut args, "--stmt-cost");
+    let Ok(optimize) = optimize_arg(&mut args) else {
+        return Ok(());
+    };
+    if has_arg(&mut args, "--list-warnings") {
+        list_warnings();
+        return Ok(());
+    }
+    let Ok(werror) = werror_arg(&mut args) else {
+        return Ok(());
+    };
+    let Ok(json_errors) = error_format_arg(&mut args) else {
+        return Ok(());
+    };
+    let Ok(tab_width) = tab_width_arg(&mut args) else {
+        return Ok(());
+    };
+    let flags = Flags {
+        debug,
+        emit_tokens_json,
+        deadcode,
+        optimize,
+        werror,
+        emit,
+        cycles: has_arg(&mut args, "--cycles"),
+        annotate: has_arg(&mut args, "--annotate"),
+        symbols: has_arg(&mut args, "--symbols"),
+        json_errors,
+        tab_width,
+    };
+    let watch = has_arg(&mut args, "--watch");
+    if let Some(result) = file_tool(&mut args, flags.tab_width.unwrap_or(TAB_WIDTH)) {
+        return result;
+    }
 
     let program = match args.pop_front() {
         None => input("Enter program or leave empty for repl: ")?,
         Some(p) => p,
     };
 
+    let tab_width = flags.tab_width.unwrap_or(TAB_WIDTH);
     if program.is_empty() {
-        return repl();
+        return repl(tab_width);
     }
 
-    let dir = if fs::metadata(format!("{program}/{program}.🖥️")).is_ok()
-        || matches!(env::current_dir(), Ok(p) if p.ends_with("programs"))
-    {
-        program.clone()
-    } else {
-        format!("programs/{program}")
-    };
-    let path = format!("{dir}/{program}.🖥️");
-    let Ok(mut file) = File::open(path.clone()) else {
-        if input("Program doesn't exist, create? [Y/n]: ")?.as_str() == "n" {
-            return Ok(());
-        }
-        create_dir_all(dir).expect("something went wrong with creating the directory");
-        fs::write(path, "").expect("something went wrong with writing the program");
+    let Some((dir, path, code)) = read_program(&program)? else {
         return Ok(());
     };
+    compile(&program, &dir, &path, &code, &flags)?;
+    if watch {
+        watch_program(&program, &dir, &path, &flags)?;
+    }
+    Ok(())
+}
 
-    let mut code = String::new();
-    file.read_to_string(&mut code)?;
+/// recompiles the program every time its source file changes
+fn watch_program(program: &str, dir: &str, path: &str, flags: &Flags) -> io::Result<()> {
+    report!("{}", "Watching for changes...".truecolor(19, 161, 14));
+    let mut last_modified = fs::metadata(path)?.modified()?;
+    loop {
+        thread::sleep(WATCH_INTERVAL);
+        let Some(modified) = missing_is_none(fs::metadata(path).and_then(|meta| meta.modified()))?
+        else {
+            continue;
+        };
+        if modified == last_modified {
+            continue;
+        }
+        let Some(code) = missing_is_none(fs::read_to_string(path))? else {
+            continue;
+        };
+        last_modified = modified;
+        report!();
+        compile(program, dir, path, &code, flags)?;
+    }
+}
+
+/// editors that save by replacing the file remove it for a moment, the watch
+/// loop treats that like an unchanged file
+fn missing_is_none<T>(result: io::Result<T>) -> io::Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
 
-    let tokens = match tokenize(code.as_str()) {
+/// tokenizes, parses and compiles `code`, then saves the output.
+/// Errors in the program are printed, only io errors are returned
+fn compile(program: &str, dir: &str, path: &str, code: &str, flags: &Flags) -> io::Result<()> {
+    let Some(options) = compile_options(code, path, flags) else {
+        return Ok(());
+    };
+    let tokens = match tokenize(code) {
         Ok(tokens) => tokens,
         Err(err) => {
-            err.pretty_print(code.as_str(), path.as_str());
+            print_error(&err, code, path, flags, options.tab_width);
             return Ok(());
         }
     };
-    if debug {
-        println!("{tokens:#?}");
+    if flags.debug {
+        report!("{tokens:#?}");
+    }
+    if flags.emit_tokens_json {
+        fs::write(
+            format!("{dir}/{program}.tokens.json"),
+            tokens_to_json(&tokens),
+        )?;
     }
 
     let mut parser = Parser::new();
     let ast = match parser.produce_ast(tokens) {
         Ok(ast) => ast,
-        Err(errs) => {
-            errs.into_iter()
-                .for_each(|err| err.pretty_print(code.as_str(), path.as_str()));
+        Err(mut errs) => {
+            errs.sort_by_key(|err| err.location);
+            for err in errs {
+                print_error(&err, code, path, flags, options.tab_width);
+            }
             return Ok(());
         }
     };
-    if debug {
-        println!("{ast:#?}");
+    if flags.debug {
+        report!("{ast:#?}");
     }
 
-    let assembly = match compile_program(ast) {
-        Ok(assembly) => assembly,
-        Err(errs) => {
+    let passes: Vec<_> = options.passes().into_iter().map(Pass::name).collect();
+    let tab_width = options.tab_width;
+    let compilation = match compile_with_details(ast, options) {
+        Ok(compiled) => compiled,
+        Err(mut errs) => {
+            errs.sort_by_key(|err| err.location);
             for err in errs {
-                err.pretty_print(code.as_str(), path.as_str());
+                print_error(&err, code, path, flags, tab_width);
             }
             return Ok(());
         }
     };
+    if flags.debug {
+        report!("Optimization passes: {passes:?}");
+    }
 
-    let mut asm_string = String::new();
-    assembly
-        .iter()
-        .map(|instr| format!("{instr}\n"))
-        .for_each(|line| asm_string.push_str(line.as_str()));
+    if flags.deadcode {
+        print_statement_costs(&compilation.statement_costs, code);
+    }
+    let assembly = compilation.instructions;
 
-    fs::write(format!("{dir}/{program}.asm"), asm_string)?;
+    let source = flags.annotate.then_some(code);
+    let saved = save_output(&assembly, dir, program, source, &flags.emit)?;
 
-    let mut bin_string = String::new();
-    assembly
+    report!("{}", "Compilation finished successful".bright_green());
+    if !saved.is_empty() {
+        report!(
+            "{} {}",
+            "Saved output to".truecolor(19, 161, 14),
+            saved.join(", ").truecolor(222, 222, 222)
+        );
+    }
+    if flags.cycles {
+        print_cycles(&assembly);
+    }
+    if flags.symbols {
+        print_symbols(&compilation.symbols);
+    }
+    for warning in compilation.warnings {
+        print_warning(&warning, code, path, flags, tab_width);
+    }
+
+    Ok(())
+}
+
+fn print_symbols(symbols: &Symbols) {
+    for (name, slot) in &symbols.variables {
+        report!(
+            "{:<8} {name}",
+            format!("slot {slot}").truecolor(19, 161, 14)
+        );
+    }
+    for (name, value) in &symbols.inline_variables {
+        report!("{:<8} {name} = {value}", "inline".truecolor(19, 161, 14));
+    }
+    for (name, base, size) in &symbols.arrays {
+        report!(
+            "{:<8} {name}[{size}] at {base}",
+            "array".truecolor(19, 161, 14)
+        );
+    }
+    for (key, slot) in &symbols.module_slots {
+        report!("{:<8} {key}", format!("slot {slot}").truecolor(19, 161, 14));
+    }
+}
+
+/// sums the cost of every instruction once, ignoring jumps
+fn print_cycles(assembly: &[Instruction]) {
+    let ticks: usize = assembly
         .iter()
-        .map(|instr| format!("{:016b}\n", instr.to_bin()))
-        .for_each(|line| bin_string.push_str(line.as_str()));
-
-    fs::write(format!("{dir}/{program}.bin"), bin_string)?;
-
-    if has_arg(&mut args, "--loc") {
-        let mut locations = String::new();
-        let mut last = None;
-        for instr in &assembly {
-            let line_s = (instr.orig_location.0 .0, instr.orig_location.1 .0);
-            if last != Some(line_s) {
-                locations.push_str(&if line_s.0 == line_s.1 {
-                    format!("{}:\n", line_s.0 + 1)
-                } else {
-                    format!("{}-{}:\n", line_s.0 + 1, line_s.1 + 1)
-                });
-                last = Some(line_s);
+        .map(|instr| usize::from(instr.variant.cycles()))
+        .sum();
+    report!(
+        "{} {ticks} ticks for {} instructions (straight-line, worst case)",
+        "Estimated".truecolor(19, 161, 14),
+        assembly.len()
+    );
+}
+
+/// finds the program's directory and reads its source,
+/// offering to create it if it doesn't exist yet
+fn read_program(program: &str) -> io::Result<Option<(String, String, String)>> {
+    let dir = if fs::metadata(format!("{program}/{program}.🖥️")).is_ok()
+        || matches!(env::current_dir(), Ok(p) if p.ends_with("programs"))
+    {
+        program.to_string()
+    } else {
+        format!("programs/{program}")
+    };
+    let path = format!("{dir}/{program}.🖥️");
+    let Ok(mut file) = File::open(path.clone()) else {
+        if input("Program doesn't exist, create? [Y/n]: ")?.as_str() != "n" {
+            create_dir_all(dir).expect("something went wrong with creating the directory");
+            fs::write(path, "").expect("something went wrong with writing the program");
+        }
+        return Ok(None);
+    };
+
+    let mut code = String::new();
+    file.read_to_string(&mut code)?;
+    Ok(Some((dir, path, code)))
+}
+
+fn print_statement_costs(costs: &[(Range, usize)], code: &str) {
+    let lines: Vec<_> = code.split('\n').collect();
+    for (location, count) in costs {
+        let line = location.0 .0 as usize;
+        let text = lines.get(line).map_or("", |l| l.trim());
+        let cost = format!("{count:>4} instr");
+        if *count == 0 {
+            report!("{} {:>4} | {text}", cost.yellow(), line + 1);
+        } else {
+            report!("{cost} {:>4} | {text}", line + 1);
+        }
+    }
+}
+
+/// writes the artifacts selected by `emit`, returning the paths written
+fn save_output(
+    assembly: &[Instruction],
+    dir: &str,
+    program: &str,
+    source: Option<&str>,
+    emit: &Emit,
+) -> io::Result<Vec<String>> {
+    if emit.stdout {
+        print!("{}", asm_string(assembly, source));
+    }
+    let mut saved = vec![];
+    for artifact in &emit.artifacts {
+        let contents = match artifact {
+            Artifact::Asm => asm_string(assembly, source),
+            Artifact::Bin => bin_string(assembly),
+            Artifact::Loc => locations(assembly),
+            Artifact::Hex => to_intel_hex(assembly),
+        };
+        let path = format!("{dir}/{program}.{}", artifact.extension());
+        fs::write(&path, contents)?;
+        saved.push(path);
+    }
+    Ok(saved)
+}
+
+/// one instruction per line, with the first one of every source line
+/// followed by that line as a comment if the source is given
+fn asm_string(assembly: &[Instruction], source: Option<&str>) -> String {
+    let lines: Vec<_> = source.map_or_else(Vec::new, |code| code.split('\n').collect());
+    let mut asm_string = String::new();
+    let mut last = None;
+    for instr in assembly {
+        let line = instr.orig_location.0 .0;
+        match lines.get(line as usize).map(|text| text.trim()) {
+            Some(text) if last != Some(line) && !text.is_empty() => {
+                let _ = writeln!(asm_string, "{instr} ; {text}");
+            }
+            _ => {
+                let _ = writeln!(asm_string, "{instr}");
             }
-            locations.push_str(&format!("\t{instr}\n"));
         }
-        fs::write(format!("{dir}/{program}.loc"), locations)?;
+        last = Some(line);
     }
+    asm_string
+}
 
+fn bin_string(assembly: &[Instruction]) -> String {
+    let mut bin_string = String::new();
+    for instr in assembly {
+        let _ = writeln!(bin_string, "{:016b}", instr.to_bin());
+    }
+    bin_string
+}
+
+/// runs `asm <file.asm>` or `disasm <file.bin>`. Without a file they are
+/// the names of programs, so programs called `asm` or `disasm` still compile
+fn file_tool(args: &mut VecDeque<String>, tab_width: usize) -> Option<io::Result<()>> {
+    let run: fn(&str, usize) -> io::Result<()> = match args.front()?.as_str() {
+        "asm" if args.len() > 1 => assemble,
+        "disasm" if args.len() > 1 => disassemble,
+        _ => return None,
+    };
+    let tool = args.pop_front().unwrap_or_default();
+    let path = args.pop_front().unwrap_or_default();
+    if !Path::new(&path).is_file() {
+        cli_error(format_args!("{tool} needs an existing file, got '{path}'"));
+        return Some(Ok(()));
+    }
+    Some(run(&path, tab_width))
+}
+
+/// re-encodes a (hand-edited) `.asm` file into a `.bin` next to it
+fn assemble(path: &str, tab_width: usize) -> io::Result<()> {
+    let asm = fs::read_to_string(path)?;
+    let assembly = match parse_asm(&asm) {
+        Ok(assembly) => assembly,
+        Err(err) => {
+            err.pretty_print(&asm, path, tab_width);
+            return Ok(());
+        }
+    };
+    let bin_path = Path::new(path).with_extension("bin");
+    fs::write(&bin_path, bin_string(&assembly))?;
     println!(
-        "{}\n{} {}",
-        "Compilation finished successful".bright_green(),
-        "Saved assembly to".truecolor(19, 161, 14),
-        format!("{dir}/{program}.asm").truecolor(222, 222, 222)
+        "{} {}",
+        "Saved binary to".truecolor(19, 161, 14),
+        bin_path.display().to_string().truecolor(222, 222, 222)
     );
+    Ok(())
+}
 
+/// prints the assembly of a `.bin` file
+fn disassemble(path: &str, tab_width: usize) -> io::Result<()> {
+    let bin = fs::read_to_string(path)?;
+    match parse_bin(&bin) {
+        Ok(assembly) => assembly.iter().for_each(|instr| println!("{instr}")),
+        Err(err) => err.pretty_print(&bin, path, tab_width),
+    }
     Ok(())
 }
 
+fn locations(assembly: &[Instruction]) -> String {
+    let mut locations = String::new();
+    let mut last = None;
+    for instr in assembly {
+        let line_s = (instr.orig_location.0 .0, instr.orig_location.1 .0);
+        if last != Some(line_s) {
+            if line_s.0 == line_s.1 {
+                let _ = writeln!(locations, "{}:", line_s.0 + 1);
+            } else {
+                let _ = writeln!(locations, "{}-{}:", line_s.0 + 1, line_s.1 + 1);
+            }
+            last = Some(line_s);
+        }
+        let _ = writeln!(locations, "\t{instr}");
+    }
+    locations
+}
+
 fn input(prompt: &str) -> Result<String, io::Error> {
     let mut contents = String::new();
     print!("{prompt}");
@@ -172,20 +700,34 @@ fn input(prompt: &str) -> Result<String, io::Error> {
     Ok(contents.trim().to_owned())
 }
 
-fn repl() -> io::Result<()> {
+fn repl(tab_width: usize) -> io::Result<()> {
     let mut parser = Parser::new();
+    // every line that compiled so far, they are compiled again with each new
+    // line so declarations carry over and locations point into the session
+    let mut history = String::new();
+    let mut warned = vec![];
+    let mut listing: Vec<Instruction> = vec![];
     println!("Repl v{VERSION}");
     loop {
-        let line = input("> ")?;
+        let mut line = input("> ")?;
         if line.as_str() == "exit" {
             return io::Result::Ok(());
         }
 
-        let tokens = tokenize(line.as_str());
+        // keep reading until every block is closed
+        let tokens = loop {
+            match tokenize(line.as_str()) {
+                Ok(tokens) if open_blocks(&tokens) > 0 => {
+                    line.push('\n');
+                    line.push_str(&input("... ")?);
+                }
+                tokens => break tokens,
+            }
+        };
         let tokens = match tokens {
             Ok(tokens) => tokens,
             Err(err) => {
-                err.pretty_print(&line, "Repl");
+                err.pretty_print(&line, "Repl", tab_width);
                 continue;
             }
         };
@@ -195,20 +737,52 @@ fn repl() -> io::Result<()> {
 
         let ast = match parser_result {
             Ok(ast) => ast,
-            Err(errs) => {
-                errs.into_iter()
-                    .for_each(|err| err.pretty_print(&line, "Repl"));
+            Err(mut errs) => {
+                errs.sort_by_key(|err| err.location);
+                for err in errs {
+                    err.pretty_print(&line, "Repl", tab_width);
+                }
                 continue;
             }
         };
         println!("{ast:#?}");
 
-        let code = compile_program(ast);
-        match code {
-            Ok(code) => println!("{code:#?}"),
-            Err(err) => err.into_iter().for_each(|err| {
-                err.pretty_print(&line, "Repl");
-            }),
+        let source = if history.is_empty() {
+            line
+        } else {
+            format!("{history}\n{line}")
+        };
+        let compiled = tokenize(&source)
+            .map_err(|err| vec![err])
+            .and_then(|tokens| parser.produce_ast(tokens))
+            .and_then(compile_with_warnings);
+        match compiled {
+            Ok((code, warnings)) => {
+                for warning in warnings {
+                    if !warned.contains(&warning) {
+                        warning.pretty_print(&source, "Repl", tab_width);
+                        warned.push(warning);
+                    }
+                }
+                // optimisations can change earlier lines, so show everything
+                // from the first instruction that differs from the last listing
+                let unchanged = listing
+                    .iter()
+                    .zip(&code)
+                    .take_while(|(old, new)| old == new)
+                    .count();
+                for instruction in &code[unchanged..] {
+                    println!("{instruction}");
+                }
+                listing = code;
+                history = source;
+            }
+            Err(mut errs) => {
+                errs.sort_by_key(|err| err.location);
+                for err in errs {
+                    err.pretty_print(&source, "Repl", tab_width);
+                }
+            }
         }
     }
 }
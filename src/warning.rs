@@ -0,0 +1,126 @@
+use std::fmt::{Debug, Display};
+
+use crate::{
+    error::{diagnostic_json, print_diagnostic, Error, ErrorType, BRIGHT_YELLOW, YELLOW},
+    frontend::Range,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    UnusedVariable(String),
+    UnreachableCode,
+    PartialAssignment(String),
+}
+
+impl Type {
+    #[must_use]
+    pub const fn category(&self) -> Category {
+        match self {
+            Self::UnusedVariable(..) => Category::Unused,
+            Self::UnreachableCode => Category::Unreachable,
+            Self::PartialAssignment(..) => Category::Branches,
+        }
+    }
+}
+
+impl ErrorType for Type {
+    fn get_message(&self) -> String {
+        match self {
+            Self::UnusedVariable(name) => format!("Variable {name} is never read"),
+            Self::UnreachableCode => "This code is never reached".to_string(),
+            Self::PartialAssignment(name) => {
+                format!("Variable {name} is only assigned in some branches")
+            }
+        }
+    }
+}
+
+/// Stable names for groups of warnings, as accepted by `--werror-on`
+///
+/// - `unused`: variables that are never read
+/// - `unreachable`: statements after `stop` or a `forever` without `break`
+/// - `branches`: variables an `if` with an `else` only assigns in some branches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Unused,
+    Unreachable,
+    Branches,
+}
+
+impl Category {
+    pub const ALL: [Self; 3] = [Self::Unused, Self::Unreachable, Self::Branches];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Unused => "unused",
+            Self::Unreachable => "unreachable",
+            Self::Branches => "branches",
+        }
+    }
+
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::Unused => "variables that are never read",
+            Self::Unreachable => "statements after `stop` or a `forever` without `break`",
+            Self::Branches => "variables an `if` with an `else` only assigns in some branches",
+        }
+    }
+
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.name() == name)
+    }
+}
+
+/// A problem that doesn't stop compilation
+#[derive(Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub typ: Type,
+    pub location: Range,
+}
+
+impl Warning {
+    /// one line of JSON for tools, see [`diagnostic_json`]
+    #[must_use]
+    pub fn to_json(&self, file: &str) -> String {
+        diagnostic_json("warning", &self.typ.get_message(), self.location, file)
+    }
+
+    /// prints the warning with its line, tabs expanded to `tab_width`
+    pub fn pretty_print(&self, code: &str, file: &str, tab_width: usize) {
+        print_diagnostic(
+            ("Warning:", YELLOW, BRIGHT_YELLOW),
+            &self.typ.get_message(),
+            self.location,
+            code,
+            file,
+            tab_width,
+        );
+    }
+}
+
+/// used when a warning is promoted to an error
+impl From<Warning> for Error {
+    fn from(warning: Warning) -> Self {
+        Self {
+            typ: Box::new(warning.typ),
+            location: warning.location,
+        }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {:?}", self.typ.get_message(), self.location)
+    }
+}
+
+impl Debug for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
@@ -0,0 +1,1273 @@
+mod compiler_tests {
+    use redstone_compiler::{
+        backend::{
+            compile_program, compile_with_details, compile_with_options, compile_with_warnings,
+            Options, Pass,
+        },
+        compile_source,
+        frontend::{tokenize, Parser},
+        warning::Category,
+        Error,
+    };
+
+    fn compile(code: &str) -> Result<Vec<String>, Vec<Error>> {
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(tokens).expect("Code to parse");
+        Ok(compile_program(ast)?
+            .iter()
+            .map(ToString::to_string)
+            .collect())
+    }
+
+    fn options_at(optimize: u8) -> Options {
+        Options {
+            optimize,
+            ..Options::default()
+        }
+    }
+
+    fn messages(code: &str) -> Vec<String> {
+        compile(code)
+            .expect_err("Code to fail compiling")
+            .into_iter()
+            .map(|err| err.to_string())
+            .collect()
+    }
+
+    fn warnings(code: &str) -> Vec<String> {
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(tokens).expect("Code to parse");
+        let (_, warnings) = compile_with_warnings(ast).expect("Code to compile");
+        warnings.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn nested_loop_jumps() {
+        let code = "var x\nwhile x < 5\n  x = 3\n  forever\n    x = 2\n  end\nend";
+        let expected = vec![
+            "LA 0", "LBL 5", "JGE 8", "LAL 3", "SVA 0", "LAL 2", "SVA 0", "JMP 5",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn repeat_unrolled() {
+        let code = "repeat 2 times\n  x = 1\nend";
+        let expected = vec!["LAL 1", "SVA 0", "LAL 1", "SVA 0"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn repeat_looped() {
+        let code = "repeat 10 times\n  x = 1\nend";
+        let expected = vec![
+            "LAL 10", "SVA 31", "LAL 1", "SVA 0", "LA 31", "LBL 1", "SUB", "SVA 31", "LBL 0",
+            "JNE 2",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn common_subexpressions() {
+        let code = "var x\nvar y\na = x*y + y*x";
+        let expected = vec!["LA 0", "LB 1", "MUL", "SVA 31", "SUP 1", "SVA 2"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        let code = "var x\nb = (x + 1) * (x + 1) - (x + 1)";
+        let expected = vec![
+            "LA 0", "LBL 1", "ADD", "SVA 31", "LB 31", "MUL", "SUB", "SVA 1",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        let tokens = tokenize("var x\nvar y\na = x*y + x*y").expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(tokens).expect("Code to parse");
+        let compiled = compile_with_options(ast, options_at(0))
+            .expect("Code to compile")
+            .0;
+        assert_eq!(
+            vec!["LA 0", "LB 1", "MUL", "SVA 31", "LA 0", "MUL", "LB 31", "ADD", "SVA 2"],
+            compiled.iter().map(ToString::to_string).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn source() {
+        assert_eq!(
+            vec!["LAL 3", "SVA 0"],
+            compile_source("x = 3")
+                .expect("Code to compile")
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        );
+        // the pragma line applies
+        assert_eq!(
+            4,
+            compile_source("# pragma optimize=0\nvar x\ny = x * 8")
+                .expect("Code to compile")
+                .len()
+        );
+        let errors = |code| {
+            compile_source(code)
+                .expect_err("Code to fail")
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec!["Invalid character: $ at 1:5"], errors("x = $"));
+        assert_eq!(vec!["Unexpected EOF while parsing at 1:3"], errors("x ="));
+        assert_eq!(vec!["Varialble y is not defined at 1:5"], errors("x = y"));
+    }
+
+    #[test]
+    fn error_json() {
+        let errors = compile("x = y").expect_err("Code to fail compiling");
+        assert_eq!(
+            r#"{"severity":"error","message":"Varialble y is not defined","file":"dir\\a.🖥️","start":{"line":0,"column":5},"end":{"line":0,"column":5}}"#,
+            errors[0].to_json("dir\\a.🖥️")
+        );
+    }
+
+    #[test]
+    fn temp_slots() {
+        // the temp of math.abs stays out of the way of the loop variables
+        let code = "use math\nvar a\nvar b\nwhile a < 10\n  b = math.abs(a - b)\n  a += 1\nend";
+        let expected = vec![
+            "LA 0", "LBL 10", "JGE 18", "LB 1", "SUB", "LBL 0", "JGE 11", "SVA 31", "LAL 0",
+            "LB 31", "SUB", "SVA 1", "LAL 1", "LB 0", "ADD", "SVA 0", "LBL 10", "JL 3",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // the list pointer doesn't share a slot with x
+        let code = "use list\nx = 5\nlist.add(x)\ny = x";
+        let expected = vec![
+            "LAL 5", "SVA 0", "LB 31", "RC", "RW", "LAL 1", "ADD", "SVA 31", "LA 0", "SVA 1",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn repeat_index() {
+        let code = "repeat 4 times as i\n  x = i * 8\nend";
+        let expected = vec![
+            "LAL 0", "SVA 0", "LAL 8", "SVA 0", "LAL 16", "SVA 0", "LAL 24", "SVA 0",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // the index is inline, so the loop gets unrolled past the limit too
+        let code = "repeat 6 times as i\n  x = i\nend";
+        assert_eq!(12, compile(code).expect("Code to compile").len());
+
+        // longer loops count up in a temp the index refers to
+        let code = "repeat 20 times as i\n  x = i\nend";
+        let expected = vec![
+            "LAL 0", "SVA 31", "SVA 0", "LA 31", "LBL 1", "ADD", "SVA 31", "LBL 20", "JL 2",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // the index can't be changed, whether the loop is unrolled or not
+        for count in [4, 20] {
+            for assignment in ["i = 5", "i += 1", "i++", "if i == 2\n    i = 0\n  end"] {
+                let code = format!("repeat {count} times as i\n  {assignment}\nend");
+                let errors = messages(&code);
+                assert_eq!(1, errors.len(), "{code}");
+                assert!(
+                    errors[0].starts_with("i is the index of the loop, it can't be assigned to"),
+                    "{code}: {errors:?}"
+                );
+            }
+        }
+        // a param of a function called in the loop is another variable
+        let code = "fn f(i)\n  i = 2\nend\nrepeat 20 times as i\n  f(i)\nend";
+        assert!(compile(code).is_ok());
+    }
+
+    #[test]
+    fn program_too_large() {
+        let assignments = |count| {
+            (0..count)
+                .map(|i| format!("x = {i}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        assert_eq!(
+            256,
+            compile(&assignments(128)).expect("Code to compile").len()
+        );
+        assert_eq!(
+            vec!["The program is too large, 258 instructions don't fit into the ROM at 129:5-7"],
+            messages(&assignments(129))
+        );
+    }
+
+    #[test]
+    fn too_many_jump_marks() {
+        let code = format!(
+            "var x\n{}",
+            "repeat 16 times as i\n  if x == i\n    pass\n  end\nend\n".repeat(6)
+        );
+        assert_eq!(
+            vec!["The program needs 300 jump marks, only 256 fit at 28:11"],
+            messages(&code)
+        );
+    }
+
+    #[test]
+    fn repeat_non_constant() {
+        assert_eq!(
+            vec!["The repeat count has to be known at compile-time at 2:8"],
+            messages("var n\nrepeat n times\n  pass\nend")
+        );
+    }
+
+    #[test]
+    fn self_addition_shift() {
+        let expected = vec!["LA 0", "SUP 1", "SVA 1"];
+        assert_eq!(
+            expected,
+            compile("var x\ny = x + x").expect("Code to compile")
+        );
+
+        let expected = vec!["LA 0", "SUP 2", "SVA 1"];
+        let code = "var x\ny = x + x + x + x";
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        let expected = vec!["LA 0", "SUP 1", "LB 0", "ADD", "SVA 1"];
+        let code = "var x\ny = x + (x + x)";
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn multiplication_shift() {
+        let expected = vec!["LA 0", "SUP 3", "SVA 1"];
+        assert_eq!(
+            expected,
+            compile("var x\ny = x * 8").expect("Code to compile")
+        );
+        assert_eq!(
+            expected,
+            compile("var x\ny = 8 * x").expect("Code to compile")
+        );
+
+        let expected = vec!["LA 0", "LBL 6", "MUL", "SVA 1"];
+        assert_eq!(
+            expected,
+            compile("var x\ny = x * 6").expect("Code to compile")
+        );
+    }
+
+    #[test]
+    fn statement_cost() {
+        let tokens = tokenize("inline a = 3\nx = a").expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(tokens).expect("Code to parse");
+        let costs: Vec<_> = compile_with_details(ast, Options::default())
+            .expect("Code to compile")
+            .statement_costs
+            .into_iter()
+            .map(|(_, count)| count)
+            .collect();
+        assert_eq!(vec![0, 2], costs);
+
+        // counted after optimizing, the load of x is gone
+        let tokens = tokenize("x = 5\ny = x").expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(tokens).expect("Code to parse");
+        let costs: Vec<_> = compile_with_details(ast, Options::default())
+            .expect("Code to compile")
+            .statement_costs
+            .into_iter()
+            .map(|(_, count)| count)
+            .collect();
+        assert_eq!(vec![2, 1], costs);
+    }
+
+    #[test]
+    fn symbol_table() {
+        let code = "use random\nx = 1\ninline c = 4\nvar a[3]\nvar b[2]\ny = x";
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(tokens).expect("Code to parse");
+        let symbols = compile_with_details(ast, Options::default())
+            .expect("Code to compile")
+            .symbols;
+        assert_eq!(
+            vec![("x".to_string(), 0), ("y".to_string(), 1)],
+            symbols.variables
+        );
+        assert_eq!(vec![("c".to_string(), 4)], symbols.inline_variables);
+        assert_eq!(
+            vec![("b".to_string(), 4091, 2), ("a".to_string(), 4093, 3)],
+            symbols.arrays
+        );
+        assert_eq!(vec![("random_state", 31)], symbols.module_slots);
+    }
+
+    #[test]
+    fn inline_overflow() {
+        assert_eq!(
+            vec!["The result of this expression doesn't fit in 16 bits at 2:12-18"],
+            messages("inline a = 200\ninline b = a * 200")
+        );
+        assert_eq!(
+            vec!["This expression cannot be used in an inline expression at 2:16"],
+            messages("var x\ninline b = 2 + x")
+        );
+        assert_eq!(
+            vec!["LAL 32", "LAH 78", "SVA 0"],
+            compile("inline a = 200\nx = a * 100").expect("Code to compile")
+        );
+    }
+
+    #[test]
+    fn colorscreen_module() {
+        let code = "use colorscreen\ncolorscreen.fill_screen(colorscreen.red)";
+        let expected = vec![
+            "LAL 0", "LAH 224", "SVA 39", "LAL 255", "LAH 15", "SVA 38", "LAL 1", "SVA 37",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn module_constants() {
+        assert_eq!(
+            vec!["LAL 0", "LAH 224", "SVA 0"],
+            compile("use colorscreen as cs\nx = cs.red").expect("Code to compile")
+        );
+        assert_eq!(
+            vec!["LAL 1", "SVA 0"],
+            compile("use colorscreen\ninline tint = colorscreen.white + 1\nx = tint")
+                .expect("Code to compile")
+        );
+        assert_eq!(
+            vec!["This constant doesn't exist or its module isn't used at 1:5-19"],
+            messages("x = colorscreen.red")
+        );
+    }
+
+    #[test]
+    fn colorscreen_gradient() {
+        let code = "use colorscreen\ncolorscreen.gradient(0, 0, 1, 1)";
+        let expected = vec![
+            "LAL 0", "LBL 0", "OR", "SVA 39", "LAL 1", "SVA 38", "LAL 1", "SVA 37", "LAL 64",
+            "LBL 0", "LBH 128", "OR", "SVA 39", "LAL 65", "SVA 38", "LAL 1", "SVA 37",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+        let code = "use colorscreen\ncolorscreen.gradient(1, 0, 0, 1)";
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // one band per color, so every color gets written once
+        let code = "use colorscreen\ncolorscreen.gradient(0, 0, 63, 63)";
+        let compiled = compile(code).expect("Code to compile");
+        assert_eq!(16, compiled.iter().filter(|i| *i == "SVA 37").count());
+
+        // at runtime each column is filled in a loop
+        let code = "use colorscreen\nvar a\ncolorscreen.gradient(a, 0, 3, 1)";
+        let compiled = compile(code).expect("Code to compile");
+        assert_eq!(1, compiled.iter().filter(|i| *i == "SVA 37").count());
+        assert_eq!(1, compiled.iter().filter(|i| i.starts_with("JLE ")).count());
+    }
+
+    #[test]
+    fn power() {
+        // right associative, so this is 2 ** 9
+        let expected = vec!["LAL 0", "LAH 2", "SVA 0"];
+        assert_eq!(
+            expected,
+            compile("x = 2 ** 3 ** 2").expect("Code to compile")
+        );
+
+        let expected = vec!["LA 0", "LB 0", "MUL", "MUL", "SVA 1"];
+        assert_eq!(
+            expected,
+            compile("var a\nx = a ** 3").expect("Code to compile")
+        );
+
+        // a runtime exponent loops, shifting for a power of two base
+        let expected = vec![
+            "LA 0", "SVA 31", "LAL 1", "SVA 30", "LA 31", "LBL 0", "JLE 14", "LBL 1", "SUB",
+            "SVA 31", "LA 30", "SUP 2", "SVA 30", "JMP 4", "LA 30", "SVA 1",
+        ];
+        assert_eq!(
+            expected,
+            compile("var n\nx = 4 ** n").expect("Code to compile")
+        );
+
+        // constant powers wrap like MUL does
+        let expected = vec!["LAL 0", "SVA 0"];
+        assert_eq!(expected, compile("x = 16 ** 4").expect("Code to compile"));
+        let expected = vec!["LAL 0", "LAH 128", "SVA 0"];
+        assert_eq!(expected, compile("x = 2 ** 15").expect("Code to compile"));
+        // the register tracker multiplies the same way
+        let code = "use math\nx = math.max(300, 2) * 300";
+        let expected = vec!["LAL 44", "LAH 1", "LBL 44", "LBH 1", "MUL", "SVA 0"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn arrays() {
+        let code = "var buf[4]\nvar data[8]\nvar i\ndata[2] = 7\nx = data[i]";
+        let expected = vec![
+            "RC", "LBL 246", "LBH 15", "LAL 7", "RW", "RC", "LA 0", "LBL 244", "LBH 15", "ADD",
+            "SVA 31", "LB 31", "RR", "SVA 1",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        assert_eq!(
+            vec!["The index 4 is out of bounds for an array of 4 elements at 2:5"],
+            messages("var buf[4]\nbuf[4] = 1")
+        );
+        assert_eq!(
+            vec!["The array buf doesn't exist at 1:5-7"],
+            messages("x = buf[1]")
+        );
+        // assigning an array leaves no variable to load the result from
+        assert_eq!(
+            vec!["Varialble buf is not defined at 2:10-12"],
+            messages("var buf[4]\nx = 2 - (buf = [1, 2])")
+        );
+        assert_eq!(
+            vec!["The array size has to be known at compile-time at 2:9"],
+            messages("var n\nvar buf[n]")
+        );
+        assert_eq!(
+            vec!["There is no room left in RAM for 2 elements at 2:9"],
+            messages("var big[4095]\nvar buf[2]")
+        );
+    }
+
+    #[test]
+    fn arrays_and_list() {
+        // arrays sit at the end of RAM, the list grows from 0
+        let code = "use list\nvar a[2]\na = [5, 6]\nlist.add(9)";
+        let compiled = compile(code).expect("Code to compile");
+        let expected = vec!["RC", "LBL 254", "LBH 15", "LAL 5", "RW"];
+        assert_eq!(expected, compiled[..5]);
+        let expected = vec!["LAL 9", "LB 31", "RC", "RW"];
+        assert_eq!(expected, compiled[10..14]);
+    }
+
+    #[test]
+    fn array_literals() {
+        let code = "var a[3]\na = [1, 2, 3]";
+        let expected = vec![
+            "RC", "LBL 253", "LBH 15", "LAL 1", "RW", "RC", "LBL 254", "LBH 15", "LAL 2", "RW",
+            "LBL 255", "LBH 15", "LAL 3", "RW",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        let code = "use list\nlist.add([4, 5])";
+        let expected = vec![
+            "LAL 4", "LB 31", "RC", "RW", "LAL 1", "ADD", "SVA 31", "LAL 5", "LB 31", "RC", "RW",
+            "LAL 1", "ADD", "SVA 31",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        assert_eq!(
+            vec!["The index 2 is out of bounds for an array of 2 elements at 2:12"],
+            messages("var a[2]\na = [1, 2, 3]")
+        );
+        assert_eq!(
+            vec!["An array literal can only be assigned to an array or added to a list at 1:5-10"],
+            messages("x = [1, 2] ")
+        );
+    }
+
+    #[test]
+    fn increment() {
+        let code = "var x\nx = 10\nx++\nx++\nx--";
+        let expected = vec![
+            "LAL 10", "SVA 0", "LBL 1", "ADD", "SVA 0", "ADD", "SVA 0", "SUB", "SVA 0",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // x is only in B, so 1 gets loaded into A instead
+        let code = "var x\nvar y\nif y == x\n  x++\nend";
+        let expected = vec!["LA 1", "LB 0", "JNE 6", "LAL 1", "ADD", "SVA 0"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn ram_fill() {
+        let code = "use ram\nram.fill(0, 0, 2)";
+        let expected = vec!["LBL 0", "LAL 0", "RW", "LBL 1", "RW", "LBL 2", "RW"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // an empty range at runtime skips the loop
+        let code = "use ram\nvar a\nram.fill(7, 16, a)";
+        let expected = vec![
+            "LBL 16", "LA 0", "JL 10", "RC", "LAL 7", "RW", "LA 0", "JE 10", "INB", "JMP 3",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // the span doesn't fit in 16 bits, but the ends are known to be in order
+        let code = "use ram\nram.fill(0, -30000, 30000)";
+        let expected = vec![
+            "LBL 208", "LBH 138", "RC", "LAL 0", "RW", "LAL 48", "LAH 117", "JE 10", "INB", "JMP 2",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // the last address is checked before INB wraps around
+        let code = "use ram\nvar a\nram.fill(0, a, 32767)";
+        let compiled = compile(code).expect("Code to compile");
+        assert_eq!(vec!["JE 12", "INB", "JMP 4"], compiled[9..]);
+
+        let code = "use ram\nram.fill(1, 5, 2)";
+        assert_eq!(
+            Vec::<String>::new(),
+            compile(code).expect("Code to compile")
+        );
+    }
+
+    #[test]
+    fn math_module() {
+        let code = "use math\nuse io\nvar a\nio.write(math.abs(a), 0)";
+        let expected = vec![
+            "LA 0", "LBL 0", "JGE 7", "SVA 31", "LAL 0", "LB 31", "SUB", "SVA 32",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        let code = "use math\nuse io\nvar a\nvar b\nio.write(math.min(a, b), 0)";
+        let expected = vec!["LB 1", "LA 0", "JLE 4", "LA 1", "SVA 32"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        let code = "use math\nuse io\nio.write(math.max(-3, 2), 0)";
+        let expected = vec!["LAL 2", "SVA 32"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn number_loads() {
+        // LAL/LBL zero-extend and LAH/LBH add the high byte, so a zero
+        // high byte is skipped no matter the sign of the low one
+        let cases = [
+            (255, vec!["LAL 255"], vec!["LBL 255"]),
+            (256, vec!["LAL 0", "LAH 1"], vec!["LBL 0", "LBH 1"]),
+            (-1, vec!["LAL 255", "LAH 255"], vec!["LBL 255", "LBH 255"]),
+            (-256, vec!["LAL 0", "LAH 255"], vec!["LBL 0", "LBH 255"]),
+            (-32768, vec!["LAL 0", "LAH 128"], vec!["LBL 0", "LBH 128"]),
+        ];
+        for (value, a_loads, b_loads) in cases {
+            let code = format!("use io\nvar a\nio.write({value}, 0)\nio.write(a + {value}, 1)");
+            let mut expected = a_loads;
+            expected.extend(["SVA 32", "LA 0"]);
+            expected.extend(b_loads);
+            expected.extend(["ADD", "SVA 33"]);
+            assert_eq!(
+                expected,
+                compile(&code).expect("Code to compile"),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn high_byte_tracking() {
+        // LBL followed by LBH stays a known number, so B isn't reloaded
+        let code = "var a\nb = a + 0x0102\nc = a - 0x0102";
+        let expected = vec![
+            "LA 0", "LBL 2", "LBH 1", "ADD", "SVA 1", "LA 0", "SUB", "SVA 2",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn screen_line() {
+        let code = "use screen\nscreen.line(0, 0, 2, 1)";
+        let expected = vec![
+            "LAL 0", "SVA 39", "LAL 4", "SVA 38", "LAL 1", "LAH 1", "SVA 39", "LAL 4", "SVA 38",
+            "LAL 1", "LAH 2", "SVA 39", "LAL 4", "SVA 38",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // runtime ends draw every pixel from a single loop
+        let code = "use screen\nvar a\nscreen.line(0, 0, a, 2)";
+        let compiled = compile(code).expect("Code to compile");
+        assert_eq!(1, compiled.iter().filter(|i| *i == "SVA 38").count());
+        assert_eq!(Some("JMD 40"), compiled.last().map(String::as_str));
+    }
+
+    #[test]
+    fn screen_rect() {
+        // corners in any order, every pixel is set once
+        let code = "use screen\nscreen.rect(1, 1, 0, 0)";
+        let expected = vec![
+            "LAL 0", "SVA 39", "LAL 4", "SVA 38", "LAL 1", "SVA 39", "LAL 4", "SVA 38", "LAL 0",
+            "LAH 1", "SVA 39", "LAL 4", "SVA 38", "LAL 1", "LAH 1", "SVA 39", "LAL 4", "SVA 38",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // one loop for the rows and one for the columns
+        let code = "use screen\nvar a\nscreen.rect(0, 0, a, a)";
+        let compiled = compile(code).expect("Code to compile");
+        assert_eq!(4, compiled.iter().filter(|i| *i == "SVA 38").count());
+        assert_eq!(2, compiled.iter().filter(|i| i.starts_with("JLE")).count());
+        // which counts from the smaller corner, whichever one that is
+        assert_eq!(2, compiled.iter().filter(|i| i.starts_with("JG ")).count());
+    }
+
+    #[test]
+    fn colorscreen_line() {
+        let code = "use colorscreen\ncolorscreen.line(0, 0, 1, 1, colorscreen.red)";
+        let expected = vec![
+            "LAL 0", "LAH 224", "SVA 39", "SVA 38", "LAL 1", "SVA 37", "LAL 65", "LAH 224",
+            "SVA 39", "SVA 38", "LAL 1", "SVA 37",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        let code = "use colorscreen\nvar c\ncolorscreen.line(0, 0, 1, 1, c)";
+        let expected = vec![
+            "LAL 0", "LB 0", "OR", "SVA 39", "SVA 38", "LAL 1", "SVA 37", "LAL 65", "OR", "SVA 39",
+            "SVA 38", "LAL 1", "SVA 37",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn off_screen() {
+        assert_eq!(
+            vec!["The coordinate 64 is off the screen, it has to be from 0 to 63 at 2:15-16"],
+            messages("use screen\nscreen.set_at(64, 3)")
+        );
+        assert_eq!(
+            vec!["The coordinate -1 is off the screen, it has to be from 0 to 63 at 2:23-24"],
+            messages("use colorscreen\ncolorscreen.set_at(1, -1, colorscreen.red)")
+        );
+        assert_eq!(
+            vec!["The coordinate 70 is off the screen, it has to be from 0 to 63 at 2:19-20"],
+            messages("use screen\nscreen.line(0, 0, 70, 0)")
+        );
+    }
+
+    #[test]
+    fn io_slot_names() {
+        let code = "use io\nio.write(io.read(io.slot2), io.slot7)\nio.write(1, 3)";
+        let expected = vec!["LA 34", "SVA 39", "LAL 1", "SVA 35"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        assert_eq!(
+            vec!["This constant doesn't exist or its module isn't used at 2:13-20"],
+            messages("use io\nio.write(1, io.slot8)")
+        );
+
+        // the names are ordinary constants
+        let code = "use io\nx = io.slot3\nio.write(x, io.slot1)";
+        let expected = vec!["LAL 3", "SVA 0", "SVA 33"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn io_read_runtime_slot() {
+        let code = "use io\nvar s\nio.write(io.read(s), 0)";
+        let compiled = compile(code).expect("Code to compile");
+        assert_eq!(["LB 0", "LAL 0", "JNE 5", "LA 32", "JMP 30"], compiled[..5]);
+        assert_eq!(["LA 39", "SVA 32"], compiled[compiled.len() - 2..]);
+        assert_eq!(7, compiled.iter().filter(|i| *i == "JMP 30").count());
+    }
+
+    #[test]
+    fn ram_page_of_variable() {
+        // the page only has to change again once the address variable does
+        let code = "use ram\nvar a\nx = ram.read(a)\ny = ram.read(a)\na += 1\nz = ram.read(a)";
+        let expected = vec![
+            "RC", "LB 0", "RR", "SVA 1", "RR", "SVA 2", "LAL 1", "ADD", "SVA 0", "RC", "LB 0",
+            "RR", "SVA 3",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn ram_page_in_loop() {
+        // the second iteration starts on the page the last write left selected
+        let code = "use ram\nrepeat 20 times\n ram.write(1, 5)\n ram.write(2, 100)\nend";
+        let expected = vec![
+            "LAL 20", "SVA 31", "RC", "LBL 5", "LAL 1", "RW", "RC", "LBL 100", "LAL 2", "RW",
+            "LA 31", "LBL 1", "SUB", "SVA 31", "LBL 0", "JNE 2",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // an endless loop is entered with the page of the write before it
+        let code = "use ram\nram.write(1, 100)\nwhile 1 == 1\n ram.write(2, 5)\nend";
+        let expected = vec![
+            "RC", "LBL 100", "LAL 1", "RW", "RC", "LBL 5", "LAL 2", "RW", "JMP 4",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn random_module() {
+        let code = "use random\nuse io\nrandom.seed(7)\nio.write(random.next(), 0)";
+        let expected = vec![
+            "LAL 52", "LAH 18", "SVA 31", "LAL 7", "SVA 31", "LBL 0", "JGE 11", "SUP 1", "LBL 45",
+            "XOR", "JMP 12", "SUP 1", "SVA 31", "SVA 32",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        assert_eq!(
+            vec!["The arguments The seed can't be 0 are invalid at 2:13"],
+            messages("use random\nrandom.seed(0)")
+        );
+    }
+
+    #[test]
+    fn while_condition_registers() {
+        let code = "var a\nvar b\nwhile a < b\n  x = a + b\nend";
+        let expected = vec!["LA 0", "LB 1", "JGE 7", "ADD", "SVA 2", "LA 0", "JL 3"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn redundant_loads() {
+        let code = "use ram\nvar x\nram.write(y = x + 1, 1 + 1)";
+        let expected = vec!["LA 0", "LBL 1", "ADD", "SVA 1", "LBL 2", "RW"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // the loop comes back with y in A, so the load has to stay
+        let code = "x = 1\nforever\n  y = x\nend";
+        let expected = vec!["LAL 1", "SVA 0", "LA 0", "SVA 1", "JMP 2"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn loop_constants() {
+        // B holds 1000 before the loop and at the end of every iteration
+        let code = "var x\nvar y\ny = x + 1000\nforever\n  x = x + 1000\nend";
+        let compiled = compile(code).expect("Code to compile");
+        assert_eq!(1, compiled.iter().filter(|i| i.starts_with("LBL")).count());
+        let expected = vec![
+            "LA 0", "LBL 232", "LBH 3", "ADD", "SVA 1", "LA 0", "ADD", "SVA 0", "JMP 5",
+        ];
+        assert_eq!(expected, compiled);
+
+        // the second iteration arrives with a different number
+        let code = "var x\nvar y\ny = x + 1000\nforever\n  x = x + 1000\n  y = y + 7\nend";
+        let compiled = compile(code).expect("Code to compile");
+        assert_eq!(3, compiled.iter().filter(|i| i.starts_with("LBL")).count());
+    }
+
+    #[test]
+    fn unreachable_code() {
+        let code = "forever\n  x = 1\nend\ny = 2";
+        let expected = vec!["LAL 1", "SVA 0", "JMP 0"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // the jump to the end of the if is never reached
+        let code = "var c\nif c == 1\n  forever\n    x = 1\n  end\nelse\n  x = 2\nend";
+        let expected = vec![
+            "LA 0", "LBL 1", "JNE 6", "LAL 1", "SVA 1", "JMP 3", "LAL 2", "SVA 1",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn debug() {
+        assert_eq!(vec!["LAL 17"], compile("debug").expect("Code to compile"));
+
+        let expected = vec!["LA 0", "LBL 1", "ADD", "SVA 31", "LB 31", "LAL 17"];
+        assert_eq!(
+            expected,
+            compile("var x\ndebug(x + 1)").expect("Code to compile")
+        );
+        assert_eq!(
+            vec!["LB 0", "LAL 17"],
+            compile("var x\ndebug(x)").expect("Code to compile")
+        );
+        assert_eq!(
+            vec!["LBL 5", "LAL 17"],
+            compile("debug(5)").expect("Code to compile")
+        );
+    }
+
+    #[test]
+    fn use_alias() {
+        let aliased = compile("use math as m\nuse io\nio.write(m.max(-3, 2), 0)");
+        let plain = compile("use math\nuse io\nio.write(math.max(-3, 2), 0)");
+        assert_eq!(
+            plain.expect("Code to compile"),
+            aliased.expect("Code to compile")
+        );
+
+        assert_eq!(
+            vec!["screen is already a module, it can't be an alias at 1:11-16"],
+            messages("use io as screen")
+        );
+        assert_eq!(
+            vec!["s is already an alias of screen at 2:11"],
+            messages("use screen as s\nuse io as s")
+        );
+
+        // constants resolve through the alias too
+        let aliased = compile("use io as i\ni.write(5, i.slot2)");
+        assert_eq!(vec!["LAL 5", "SVA 34"], aliased.expect("Code to compile"));
+    }
+
+    #[test]
+    fn unknown_method() {
+        assert_eq!(
+            vec!["The method sqrt doesn't exist on math, available: abs, min, max at 2:1-9"],
+            messages("use math\nmath.sqrt(4)")
+        );
+    }
+
+    #[test]
+    fn wrong_arg_count() {
+        assert_eq!(
+            vec!["max takes 2 arguments, but got 1 at 2:1-8"],
+            messages("use math\nmath.max(4)")
+        );
+        assert_eq!(
+            vec!["f takes 1 argument, but got 0 at 4:1"],
+            messages("fn f(x)\n  pass\nend\nf()")
+        );
+    }
+
+    #[test]
+    fn screen_present() {
+        let expected = vec!["LAL 1", "SVA 38", "LAL 2", "SVA 38"];
+        assert_eq!(
+            expected,
+            compile("use screen\nscreen.present()").expect("Code to compile")
+        );
+    }
+
+    #[test]
+    fn trace() {
+        let expected = vec!["LAL 42", "SVA 36"];
+        assert_eq!(expected, compile("trace 42").expect("Code to compile"));
+        assert_eq!(
+            vec!["The trace tag has to be known at compile-time at 2:7"],
+            messages("var x\ntrace x")
+        );
+        // out slot 4 is only reserved in programs that trace
+        assert_eq!(
+            vec!["LAL 1", "SVA 36"],
+            compile("use io\nio.write(1, 4)").expect("Code to compile")
+        );
+        assert_eq!(
+            vec!["Out slot 4 is where trace writes its tags, it can't be written to in a program that traces at 2:1-8"],
+            messages("use io\nio.write(1, 4)\ntrace 1")
+        );
+    }
+
+    #[test]
+    fn assert_statement() {
+        let code = "inline WIDTH = 64\nassert WIDTH == 64\nassert WIDTH - 4 < 64";
+        assert_eq!(
+            Vec::<String>::new(),
+            compile(code).expect("Code to compile")
+        );
+        assert_eq!(
+            vec!["The assertion doesn't hold at 2:8-18"],
+            messages("inline WIDTH = 32\nassert WIDTH == 64")
+        );
+        assert_eq!(
+            vec!["The assertion has to be known at compile-time at 2:13"],
+            messages("var x\nassert 1 == x")
+        );
+    }
+
+    #[test]
+    fn comparison_values() {
+        let code = "var a\nvar b\nx = a > b";
+        let expected = vec!["LA 0", "LB 1", "JLE 5", "LAL 1", "JMP 6", "LAL 0", "SVA 2"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        let expected = vec!["LAL 1", "SVA 0"];
+        assert_eq!(expected, compile("x = 3 == 3").expect("Code to compile"));
+    }
+
+    #[test]
+    fn match_statement() {
+        let code = "var s\nmatch s\ncase 0:\n  s = 1\ncase 1:\n  s = 2\nelse\n  s = 0\nend";
+        let expected = vec![
+            "LA 0", "LBL 0", "JE 8", "LBL 1", "JE 11", "LAL 0", "SVA 0", "JMP 13", "LAL 1",
+            "SVA 0", "JMP 13", "LAL 2", "SVA 0",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        let code = "inline MODE = 3\nmatch MODE\ncase 1:\n  x = 1\ncase 3:\n  x = 2\nend";
+        assert_eq!(
+            vec!["LAL 2", "SVA 0"],
+            compile(code).expect("Code to compile")
+        );
+
+        assert_eq!(
+            vec!["The case value has to be known at compile-time at 3:6-10"],
+            messages("var s\nmatch s\ncase s + 1:\n  pass\nend")
+        );
+    }
+
+    #[test]
+    fn do_while() {
+        let code = "var a\ndo\n  a += 2\nwhile a < 7";
+        let expected = vec!["LAL 2", "LB 0", "ADD", "SVA 0", "LBL 7", "JL 0"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // runs exactly once
+        let code = "var a\ndo\n  a += 2\nwhile 1 == 2";
+        let expected = vec!["LAL 2", "LB 0", "ADD", "SVA 0"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn conditional_state_merge() {
+        // both branches leave b in A and the 1 from the comparison in B
+        let code = "var a\nvar b\nif a == 1\n  b = 2\nelse\n  b = 2\nend\nc = b + 1";
+        let expected = vec![
+            "LA 0", "LBL 1", "JNE 6", "LAL 2", "SVA 1", "JMP 8", "LAL 2", "SVA 1", "ADD", "SVA 2",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // the body changes A, so a has to be reloaded afterwards
+        let code = "var a\nif a == 1\n  b = 5\nend\nc = a";
+        let expected = vec!["LA 0", "LBL 1", "JNE 5", "LAL 5", "SVA 1", "LA 0", "SVA 1"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn logical_conditions() {
+        let code = "var a\nvar b\nif a > 0 and b < 10\n  x = 1\nend";
+        let expected = vec![
+            "LA 0", "LBL 0", "JLE 8", "LA 1", "LBL 10", "JGE 8", "LAL 1", "SVA 2",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        // B still holds 1 after the first comparison
+        let code = "var a\nvar b\nif a == 1 or b == 1\n  x = 2\nend";
+        let expected = vec!["LA 0", "LBL 1", "JE 5", "LA 1", "JNE 7", "LAL 2", "SVA 2"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        let code = "var a\nx = a < 2 or 1 == 2";
+        let expected = vec!["LA 0", "LBL 2", "JGE 5", "LAL 1", "JMP 6", "LAL 0", "SVA 1"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        let code = "var a\nwhile a < 3 and (a == 0 or a == 2)\n  a += 1\nend";
+        let expected = vec![
+            "LA 0", "LBL 3", "JGE 17", "LBL 0", "JE 7", "LBL 2", "JNE 17", "LAL 1", "LB 0", "ADD",
+            "SVA 0", "LBL 3", "JGE 17", "LBL 0", "JE 7", "LBL 2", "JE 7",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn constant_conditions() {
+        assert_eq!(
+            vec!["LAL 1", "SVA 0"],
+            compile("if 1 == 1\n  x = 1\nend").expect("Code to compile")
+        );
+        assert_eq!(
+            vec!["LAL 2", "SVA 0"],
+            compile("if 1 > 2\n  x = 1\nelse\n  x = 2\nend").expect("Code to compile")
+        );
+        let code =
+            "var c\ninline n = 3\nif c == 1\n  x = 1\nelif n == 3\n  x = 2\nelse\n  x = 3\nend";
+        let expected = vec![
+            "LA 0", "LBL 1", "JNE 6", "LAL 1", "SVA 1", "JMP 8", "LAL 2", "SVA 1",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        let code = "inline n = 3\nwhile n < 2\n  x = 1\nend";
+        assert_eq!(
+            Vec::<String>::new(),
+            compile(code).expect("Code to compile")
+        );
+        // the body is gone, so x doesn't take up a slot
+        let code = "while 1 == 2\n  x = 1\nend\ny = 2";
+        assert_eq!(
+            vec!["LAL 2", "SVA 0"],
+            compile(code).expect("Code to compile")
+        );
+        let code = "while 1 == 1\n  x = 1\nend";
+        let expected = vec!["LAL 1", "SVA 0", "JMP 0"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn break_loop() {
+        let code = "var a\nwhile a < 5\n  a += 1\n  if a == 3\n    break\n  end\nend\nb = a";
+        let expected = vec![
+            "LA 0", "LBL 5", "JGE 12", "LAL 1", "LB 0", "ADD", "SVA 0", "LBL 3", "JNE 10",
+            "JMP 12", "LBL 5", "JL 3", "SVA 1",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        let code = "var x\nforever\n  x = 1\n  break\nend\ny = x";
+        let expected = vec!["LAL 1", "SVA 0", "SVA 1"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        assert_eq!(
+            vec!["'break' can only be used inside a loop at 2:3-7"],
+            messages("if 1 == 1\n  break\nend")
+        );
+    }
+
+    #[test]
+    fn continue_loop() {
+        // lands on the back-edge comparison, which has to reload A
+        let code = "var a\nwhile a < 5\n  a += 1\n  if a == 3\n    continue\n  end\n  b = a\nend";
+        let expected = vec![
+            "LA 0", "LBL 5", "JGE 14", "LAL 1", "LB 0", "ADD", "SVA 0", "LBL 3", "JNE 10",
+            "JMP 11", "SVA 1", "LA 0", "LBL 5", "JL 3",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        assert_eq!(
+            vec!["JMP 0"],
+            compile("forever\n  continue\nend").expect("Code to compile")
+        );
+        assert_eq!(
+            vec!["'continue' can only be used inside a loop at 1:1-8"],
+            messages("continue")
+        );
+    }
+
+    #[test]
+    fn stop_program() {
+        let code = "a = 1\nif a == 1\n  stop\nend\nb = 2";
+        let expected = vec!["LAL 1", "SVA 0", "LBL 1", "JNE 5", "STP", "LAL 2", "SVA 1"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        let code = "halt";
+        assert_eq!(vec!["STP"], compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn functions() {
+        let code = "var a\nvar b\nfn sub(a, b)\n  r = a - b\nend\nsub(b, a)\nsub(1, 2)";
+        let expected = vec![
+            "LA 1", "SVA 31", "LA 0", "SVA 30", "LA 31", "LB 30", "SUB", "SVA 2", "LAL 1",
+            "SVA 31", "LAL 2", "SVA 30", "LA 31", "LB 30", "SUB", "SVA 2",
+        ];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+
+        assert_eq!(
+            vec!["The function f can't call itself at 2:3"],
+            messages("fn f(a)\n  f(a)\nend\nf(1)")
+        );
+        assert_eq!(
+            vec!["The function g doesn't exist at 1:1"],
+            messages("g(1)")
+        );
+        assert_eq!(
+            vec!["The function f doesn't return anything, it can't be used as a value at 4:5"],
+            messages("fn f(a)\n  pass\nend\nx = f(1)")
+        );
+        assert_eq!(
+            vec!["The function f doesn't return anything, it can't be used as a value at 1:5"],
+            messages("y = f(1) + 2\nfn f(a)\n  pass\nend")
+        );
+    }
+
+    #[test]
+    fn function_scope() {
+        // globals are visible, the caller's locals aren't
+        let code = "var g\nfn f()\n  g = 1\nend\nf()";
+        assert_eq!(
+            vec!["LAL 1", "SVA 0"],
+            compile(code).expect("Code to compile")
+        );
+        let code = "var c\nfn f()\n  y = x\nend\nif c == 1\n  x = 2\n  f()\nend";
+        assert_eq!(vec!["Varialble x is not defined at 3:7"], messages(code));
+
+        // the scope of a failed call is closed, so use is still global
+        assert_eq!(
+            vec!["Varialble nope is not defined at 2:7-10"],
+            messages("fn f()\n  y = nope\nend\nf()\nuse math")
+        );
+
+        let code = "f(1)\nfn f(a)\n  x = a\nend";
+        assert_eq!(
+            vec!["LAL 1", "SVA 31", "SVA 0"],
+            compile(code).expect("Code to compile")
+        );
+    }
+
+    #[test]
+    fn disc_jump_target() {
+        // the if ends on the jump back to the start, which needs an LCL first
+        let code = format!(
+            "var c\nforever\n{}  if c == 1\n    c = 5\n  end\nend",
+            "  c += 1\n".repeat(20)
+        );
+        let compiled = compile(&code).expect("Code to compile");
+        let target = compiled
+            .iter()
+            .find_map(|instr| instr.strip_prefix("JNE "))
+            .expect("The if to compile to a JNE");
+        let target: usize = target.parse().expect("A numeric jump target");
+        assert_eq!("LCL 0", compiled[target]);
+    }
+
+    #[test]
+    fn jump_to_next() {
+        let code = "var c\nif c == 1\n  x = 1\nelse\n  pass\nend";
+        let expected = vec!["LA 0", "LBL 1", "JNE 5", "LAL 1", "SVA 1"];
+        assert_eq!(expected, compile(code).expect("Code to compile"));
+    }
+
+    #[test]
+    fn unused_variables() {
+        let code = "var foo\nx = 1\ny = 2\nz = y\nif z == 1\n  w = 3\nend";
+        assert_eq!(
+            vec![
+                "Variable w is never read at 6:3",
+                "Variable foo is never read at 1:5-7",
+                "Variable x is never read at 2:1",
+            ],
+            warnings(code)
+        );
+    }
+
+    #[test]
+    fn unreachable_warning() {
+        assert_eq!(
+            vec!["This code is never reached at 3:1-4:5"],
+            warnings("var x\nstop\nx = 1\nx = x")
+        );
+        assert_eq!(
+            vec!["This code is never reached at 6:3-7"],
+            warnings("var x\nif x == 1\n  forever\n    x = 1\n  end\n  x = x\nend")
+        );
+        // a break leaves the loop, so the code after it runs
+        assert!(warnings("var x\nforever\n  if x == 1\n    break\n  end\nend\nx = x").is_empty());
+    }
+
+    #[test]
+    fn partial_assignment_warning() {
+        assert_eq!(
+            vec!["Variable a is only assigned in some branches at 4:3"],
+            warnings("var a\nvar b\nif b == 1\n  a = 1\n  b = 1\nelse\n  b = 2\nend\nb = a + b")
+        );
+        assert!(warnings("var a\nvar b\nif b == 1\n  a = 1\nelse\n  a++\nend\nb = a").is_empty());
+        // without an else not assigning is the point of the if
+        assert!(warnings("var a\nvar b\nif b == 1\n  a = 1\nend\nb = a").is_empty());
+        // a branch that stops or leaves the loop doesn't reach the code after it
+        assert!(warnings("var a\nvar b\nif b == 1\n  a = 1\nelse\n  stop\nend\nb = a").is_empty());
+        assert!(warnings(
+            "var a\nvar b\nforever\n  if b == 1\n    a = 1\n  elif b == 2\n    continue\n  else\n    break\n  end\n  b = a\nend"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn pragma_optimize() {
+        let code = "# pragma optimize=0\nvar c\nif c == 1\n  x = 1\nelse\n  pass\nend";
+        let options = Options::from_pragma(code).expect("Pragma to parse");
+        assert_eq!(0, options.optimize);
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(tokens).expect("Code to parse");
+        let compiled = compile_with_options(ast, options)
+            .expect("Code to compile")
+            .0;
+        let compiled: Vec<_> = compiled.iter().map(ToString::to_string).collect();
+        let expected = vec!["LA 0", "LBL 1", "JNE 6", "LAL 1", "SVA 1", "JMP 6"];
+        assert_eq!(expected, compiled);
+        assert!(options_at(0).passes().is_empty());
+        assert_eq!(
+            vec![
+                "constant-folding",
+                "strength-reduction",
+                "common-subexpressions",
+                "remove-unreachable",
+                "remove-jumps-to-next"
+            ],
+            options_at(1)
+                .passes()
+                .into_iter()
+                .map(Pass::name)
+                .collect::<Vec<_>>()
+        );
+
+        let tokens = tokenize("x = 2 + 3").expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(tokens).expect("Code to parse");
+        let compiled = compile_with_options(ast, options_at(0))
+            .expect("Code to compile")
+            .0;
+        let compiled: Vec<_> = compiled.iter().map(ToString::to_string).collect();
+        assert_eq!(vec!["LAL 2", "LBL 3", "ADD", "SVA 0"], compiled);
+
+        // constant conditions are compared at runtime too
+        let unoptimized = |code: &str| {
+            let tokens = tokenize(code).expect("Code to tokenize");
+            let ast = Parser::new().produce_ast(tokens).expect("Code to parse");
+            let compiled = compile_with_options(ast, options_at(0))
+                .expect("Code to compile")
+                .0;
+            compiled.iter().map(ToString::to_string).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec!["LAL 1", "LBL 2", "JNE 4", "SVA 0"],
+            unoptimized("if 1 == 2\n  x = 1\nend")
+        );
+        assert_eq!(
+            vec!["LAL 2", "LBL 2", "JNE 5", "LAL 1", "JMP 6", "LAL 0", "SVA 0"],
+            unoptimized("y = 2 == 2")
+        );
+        assert_eq!(
+            vec!["LAL 1", "LBL 1", "JNE 7", "LAL 0", "SVA 0", "LAL 1", "JE 3"],
+            unoptimized("while 1 == 1\n  x = 0\nend")
+        );
+        assert_eq!(
+            vec!["LAL 2", "LBL 2", "JE 4", "JMP 6", "LAL 1", "SVA 0"],
+            unoptimized("match 2\ncase 2:\n  x = 1\nend")
+        );
+
+        let tokens = tokenize("var x\ny = x * 8").expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(tokens).expect("Code to parse");
+        let compiled = compile_with_options(ast, options_at(0))
+            .expect("Code to compile")
+            .0;
+        let compiled: Vec<_> = compiled.iter().map(ToString::to_string).collect();
+        assert_eq!(vec!["LA 0", "LBL 8", "MUL", "SVA 1"], compiled);
+
+        assert_eq!(
+            "Invalid pragma: unknown option speed at 1:1-19",
+            Options::from_pragma("# pragma speed=fast")
+                .expect_err("Pragma to fail")
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn pragma_tab_width() {
+        let options =
+            Options::from_pragma("# pragma tab_width=8 optimize=1").expect("Pragma to parse");
+        assert_eq!(8, options.tab_width);
+        assert_eq!(1, options.optimize);
+        assert_eq!(4, Options::default().tab_width);
+        for width in ["0", "17", "tab"] {
+            assert_eq!(
+                format!(
+                    "Invalid pragma: tab_width has to be 1 to 16 at 1:1-{}",
+                    19 + width.len()
+                ),
+                Options::from_pragma(&format!("# pragma tab_width={width}"))
+                    .expect_err("Pragma to fail")
+                    .to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn werror_on() {
+        let compile = |code: &str, werror: Vec<Category>| {
+            let tokens = tokenize(code).expect("Code to tokenize");
+            let ast = Parser::new().produce_ast(tokens).expect("Code to parse");
+            compile_with_options(
+                ast,
+                Options {
+                    werror,
+                    ..Options::default()
+                },
+            )
+        };
+        let code = "x = 1";
+        assert!(compile(code, vec![]).is_ok());
+        let errors = compile(code, vec![Category::Unused]).expect_err("Code to fail");
+        assert_eq!(
+            vec!["Variable x is never read at 1:1"],
+            errors.iter().map(ToString::to_string).collect::<Vec<_>>()
+        );
+        assert!(compile("x = 1\ny = x\nx = y", vec![Category::Unused]).is_ok());
+
+        assert_eq!(Some(Category::Unused), Category::from_name("unused"));
+        assert_eq!(None, Category::from_name("uninit"));
+    }
+}
@@ -0,0 +1,26 @@
+mod error_tests {
+    use redstone_compiler::error::{display_width, expand_tabs};
+
+    #[test]
+    fn expand_tabs_to_next_stop() {
+        assert_eq!("    x = 1", expand_tabs("\tx = 1", 4));
+        assert_eq!("ab  c", expand_tabs("ab\tc", 4));
+        assert_eq!("abcd    e", expand_tabs("abcd\te", 4));
+        assert_eq!("        x", expand_tabs("\t\tx", 4));
+        assert_eq!("a       b", expand_tabs("a\tb", 8));
+        assert_eq!("a b", expand_tabs("a\tb", 1));
+        assert_eq!("no tabs", expand_tabs("no tabs", 4));
+    }
+
+    #[test]
+    fn display_width_counts_expanded_columns() {
+        assert_eq!(0, display_width("\tx = 1", 0, 4));
+        assert_eq!(4, display_width("\tx = 1", 1, 4));
+        assert_eq!(5, display_width("\tx = 1", 2, 4));
+        assert_eq!(8, display_width("\tx = 1", 2, 7));
+        assert_eq!(4, display_width("ab\tc", 3, 4));
+        // past the end of the line only the existing characters count
+        assert_eq!(3, display_width("abc", 10, 4));
+        assert_eq!(3, display_width("äöü", 3, 4));
+    }
+}
@@ -0,0 +1,187 @@
+mod instruction_tests {
+    use redstone_compiler::{
+        backend::{
+            compile_program, parse_asm, parse_bin, to_intel_hex, Instruction, InstructionVariant,
+        },
+        frontend::{tokenize, Location, Parser, Range},
+    };
+
+    fn compile(code: &str) -> Vec<Instruction> {
+        let tokens = tokenize(code).expect("Code to tokenize");
+        let ast = Parser::new().produce_ast(tokens).expect("Code to parse");
+        compile_program(ast).expect("Code to compile")
+    }
+
+    #[test]
+    fn equality_ignores_location() {
+        let a = Instruction::new(InstructionVariant::LA, Some(3), Range::default());
+        let b = Instruction::new(
+            InstructionVariant::LA,
+            Some(3),
+            Range(Location(2, 1), Location(2, 5)),
+        );
+        assert_eq!(a, b);
+        assert!(!a.eq_with_location(&b));
+        assert!(a.eq_with_location(&a));
+    }
+
+    #[test]
+    fn asm_roundtrip() {
+        let compiled = compile("use ram\nvar x\nwhile x < 300\n  x += 1\n  ram.write(x, x)\nend");
+        let asm = compiled
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let parsed = parse_asm(&asm).expect("Assembly to parse");
+        assert_eq!(
+            compiled.iter().map(Instruction::to_bin).collect::<Vec<_>>(),
+            parsed.iter().map(Instruction::to_bin).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn asm_errors() {
+        let messages = |asm: &str| parse_asm(asm).expect_err("Assembly to fail").to_string();
+        assert_eq!(
+            "Invalid assembly: unknown mnemonic FOO at 2:1-3",
+            messages("LA 1\nFOO")
+        );
+        assert_eq!(
+            "Invalid assembly: ADD takes no argument at 1:1-5",
+            messages("ADD 1")
+        );
+        assert_eq!(
+            "Invalid assembly: LA takes one argument at 1:1-2",
+            messages("LA")
+        );
+        assert_eq!(
+            "Invalid assembly: 256 is not a valid argument at 1:1-6",
+            messages("LA 256")
+        );
+    }
+
+    #[test]
+    fn disassemble() {
+        let compiled = compile("use ram\nvar x\nwhile x < 300\n  x += 1\n  ram.write(x, x)\nend");
+        let decoded: Vec<_> = compiled
+            .iter()
+            .map(|instr| Instruction::from_bin(instr.to_bin()).expect("Word to decode"))
+            .collect();
+        assert_eq!(compiled, decoded);
+
+        let decode = |variant, arg| {
+            Instruction::from_bin(Instruction::new(variant, arg, Range::default()).to_bin())
+                .map(|instr| format!("{instr}"))
+        };
+        // variants sharing a byte decode to the one declared first
+        assert_eq!(Some("RW".to_string()), decode(InstructionVariant::RR, None));
+        assert_eq!(
+            Some("RC".to_string()),
+            decode(InstructionVariant::INB, None)
+        );
+        assert_eq!(
+            Some("JE 3".to_string()),
+            decode(InstructionVariant::SE, Some(3))
+        );
+        assert_eq!(
+            Some("JDE 3".to_string()),
+            decode(InstructionVariant::JDE, Some(3))
+        );
+        assert_eq!(
+            Some("SDE 3".to_string()),
+            decode(InstructionVariant::SDE, Some(3))
+        );
+
+        assert_eq!(None, Instruction::from_bin(0x01FF));
+        // ADD takes no argument
+        assert_eq!(None, Instruction::from_bin(0x0107));
+    }
+
+    #[test]
+    fn all_variants() {
+        assert_eq!(51, InstructionVariant::ALL.len());
+        for (index, variant) in InstructionVariant::ALL.into_iter().enumerate() {
+            assert_eq!(index, variant as usize, "{variant:?}");
+            assert_eq!(Some(variant), InstructionVariant::from_name(variant.name()));
+        }
+    }
+
+    #[test]
+    fn byte_roundtrip() {
+        use InstructionVariant as V;
+        // these share every field but the name, so no metadata can tell them apart
+        let collisions = [
+            (V::RR, V::RW),
+            (V::INB, V::RC),
+            (V::SMP, V::JMP),
+            (V::SE, V::JE),
+            (V::SNE, V::JNE),
+            (V::SG, V::JG),
+            (V::SGE, V::JGE),
+            (V::SL, V::JL),
+            (V::SLE, V::JLE),
+        ];
+        for variant in InstructionVariant::ALL {
+            let expected = collisions
+                .iter()
+                .find(|(shadowed, _)| *shadowed == variant)
+                .map_or(variant, |(_, first)| *first);
+            assert_eq!(
+                Some(expected),
+                InstructionVariant::from_byte(variant.to_byte()),
+                "{variant:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn intel_hex() {
+        assert_eq!(
+            ":0400000005160012CF\n:00000001FF\n",
+            to_intel_hex(&compile("x = 5"))
+        );
+    }
+
+    #[test]
+    fn cycles() {
+        let assembly =
+            parse_asm("LAL 3\nLBL 4\nMUL\nSUP 1\nRC\nINB\nRW\nJMP 0").expect("Assembly to parse");
+        let cycles: Vec<_> = assembly
+            .iter()
+            .map(|instr| instr.variant.cycles())
+            .collect();
+        assert_eq!(vec![1, 1, 8, 4, 3, 2, 1, 1], cycles);
+    }
+
+    #[test]
+    fn bin_roundtrip() {
+        let compiled = compile("use ram\nvar x\nwhile x < 300\n  x += 1\n  ram.write(x, x)\nend");
+        let bin = compiled
+            .iter()
+            .map(|instr| format!("{:016b}", instr.to_bin()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let decoded = parse_bin(&bin).expect("Binary to parse");
+        assert_eq!(
+            compiled.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            decoded.iter().map(ToString::to_string).collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            "Invalid binary: 0000000x00010110 is not a 16-bit binary word at 2:1-16",
+            parse_bin("0000010100010110\n0000000x00010110")
+                .expect_err("Binary to fail")
+                .to_string()
+        );
+        for word in ["101", "+000010100010110", "00000101000101100"] {
+            assert_eq!(
+                format!(
+                    "Invalid binary: {word} is not a 16-bit binary word at 1:1-{}",
+                    word.len()
+                ),
+                parse_bin(word).expect_err("Binary to fail").to_string()
+            );
+        }
+    }
+}
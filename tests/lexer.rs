@@ -6,7 +6,7 @@ mod lexer_tests {
     use std::iter::once;
 
     use redstone_compiler::{
-        frontend::{tokenize, EqualityOperator, Operator, TokenType},
+        frontend::{open_blocks, tokenize, tokens_to_json, EqualityOperator, Operator, TokenType},
         Error,
     };
 
@@ -54,6 +54,59 @@ mod lexer_tests {
         assert_eq!(expected, ast);
     }
 
+    #[test]
+    fn power() {
+        let expected = vec![
+            TokenType::BinaryOperator(Operator::Pow),
+            TokenType::BinaryOperator(Operator::Mult),
+            TokenType::Eof,
+        ];
+        assert_eq!(expected, token_types("** *").expect("Code to compile"));
+    }
+
+    #[test]
+    fn increment() {
+        let expected = vec![
+            TokenType::Identifier("x".to_string()),
+            TokenType::Increment,
+            TokenType::Identifier("y".to_string()),
+            TokenType::Decrement,
+            TokenType::Eof,
+        ];
+        assert_eq!(expected, token_types("x++\ny--").expect("Code to compile"));
+
+        // a number after it makes it a subtraction
+        let expected = vec![
+            TokenType::Identifier("a".to_string()),
+            TokenType::BinaryOperator(Operator::Minus),
+            TokenType::Number(-1),
+            TokenType::Eof,
+        ];
+        assert_eq!(expected, token_types("a--1").expect("Code to compile"));
+    }
+
+    #[test]
+    fn tab_indentation() {
+        let tokens = tokenize("if x == 1\n\tx = 2\nend").expect("Code to tokenize");
+        let expected = vec![
+            TokenType::If,
+            TokenType::Identifier("x".to_string()),
+            TokenType::EqOperator(EqualityOperator::EqualTo),
+            TokenType::Number(1),
+            TokenType::Identifier("x".to_string()),
+            TokenType::Equals,
+            TokenType::Number(2),
+            TokenType::End,
+            TokenType::Eof,
+        ];
+        assert_eq!(
+            expected,
+            tokens.iter().map(|t| t.typ.clone()).collect::<Vec<_>>()
+        );
+        // a tab is a single column, the diagnostics expand it when printing
+        assert_eq!("2:2", format!("{:?}", tokens[4].location));
+    }
+
     #[test]
     fn numbers() {
         let code = "0  1  3  -17  0b1011 0xffff -0b101";
@@ -65,4 +118,131 @@ mod lexer_tests {
         let ast = token_types(code).expect("Code to compile");
         assert_eq!(expected, ast);
     }
+
+    #[test]
+    fn octal_numbers() {
+        let code = "0o17 0o0 -0o10";
+        let expected: Vec<_> = [15, 0, -8]
+            .into_iter()
+            .map(TokenType::Number)
+            .chain(once(TokenType::Eof))
+            .collect();
+        let ast = token_types(code).expect("Code to compile");
+        assert_eq!(expected, ast);
+
+        assert!(token_types("0o18").is_err());
+    }
+
+    #[test]
+    fn digit_separators() {
+        let code = "1_000 0xFF_FF 0b1010_1010 -2_5";
+        let expected: Vec<_> = [1000, -1, 170, -25]
+            .into_iter()
+            .map(TokenType::Number)
+            .chain(once(TokenType::Eof))
+            .collect();
+        let ast = token_types(code).expect("Code to compile");
+        assert_eq!(expected, ast);
+
+        for code in ["1__0", "1_", "0x_FF", "0b1_ "] {
+            let error = token_types(code).expect_err("Separator to be rejected");
+            assert!(error
+                .to_string()
+                .starts_with("'_' can only be used between digits"));
+        }
+    }
+
+    #[test]
+    fn smallest_number() {
+        let expected = vec![
+            TokenType::Number(i16::MIN),
+            TokenType::Number(i16::MIN),
+            TokenType::Number(-1),
+            TokenType::Eof,
+        ];
+        let tokens = token_types("-32768 -0x8000 -0b1").expect("Code to tokenize");
+        assert_eq!(expected, tokens);
+    }
+
+    #[test]
+    fn number_overflow() {
+        let error = token_types("x = 99999").expect_err("Number to overflow");
+        assert!(error
+            .to_string()
+            .starts_with("number 99999 doesn't fit in 16 bits"));
+
+        let error = token_types("0x1_0000").expect_err("Number to overflow");
+        assert!(error
+            .to_string()
+            .starts_with("number 0x10000 doesn't fit in 16 bits"));
+
+        let error = token_types("x = -32769").expect_err("Number to overflow");
+        assert!(error
+            .to_string()
+            .starts_with("number -32769 doesn't fit in 16 bits"));
+
+        let error = token_types("0b12").expect_err("Number to be invalid");
+        assert!(error.to_string().starts_with("Invalid number: 0b12"));
+    }
+
+    #[test]
+    fn block_comments() {
+        let code = "1 #[ 2 #[ 3 ]# 4\n5 ]# 6 # ]#\n7";
+        let tokens = tokenize(code).expect("Code to compile");
+        let expected: Vec<_> = [1, 6, 7]
+            .into_iter()
+            .map(TokenType::Number)
+            .chain(once(TokenType::Eof))
+            .collect();
+        assert_eq!(
+            expected,
+            tokens.iter().map(|t| t.typ.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!(2, tokens[2].location.0 .0);
+
+        let error = token_types("x = 1\n#[ #[ ]#\ny = 2").expect_err("Comment to be unclosed");
+        assert_eq!(
+            "Block comment is never closed with ']#' at 2:1",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn tokens_json() {
+        let tokens = tokenize("if x >= 5").expect("Code to compile");
+        let expected = concat!(
+            r#"[{"type":"keyword","value":"if","start":{"line":0,"column":1},"end":{"line":0,"column":2}},"#,
+            r#"{"type":"identifier","value":"x","start":{"line":0,"column":4},"end":{"line":0,"column":4}},"#,
+            r#"{"type":"operator","value":">=","start":{"line":0,"column":6},"end":{"line":0,"column":7}},"#,
+            r#"{"type":"number","value":5,"start":{"line":0,"column":9},"end":{"line":0,"column":9}}]"#,
+        );
+        assert_eq!(expected, tokens_to_json(&tokens));
+    }
+
+    #[test]
+    fn blocks_left_open() {
+        let open = |code| open_blocks(&tokenize(code).expect("Code to lex"));
+        assert_eq!(0, open("x = 1"));
+        assert_eq!(1, open("if x == 1\n  x = 2"));
+        assert_eq!(0, open("if x == 1\n  x = 2\nelse if x == 2\n  x = 3\nend"));
+        assert_eq!(
+            1,
+            open("if x == 1\n  x = 2\nelse\n  if x == 2\n    x = 3\n  end")
+        );
+        assert_eq!(2, open("forever\n  do\n    x += 1"));
+        assert_eq!(0, open("do\n  x += 1\nwhile x < 5"));
+        // a while can't close an empty do, so it waits for its own end
+        assert_eq!(2, open("do\n  while x < 5"));
+        assert_eq!(1, open("do\n  while x < 5\n    x += 1\n  end"));
+        assert_eq!(0, open("do\n  while x < 5\n    x += 1\n  end\nwhile y < 5"));
+        // a nested loop the do still closes after
+        assert_eq!(
+            0,
+            open("do\n  y += 1\n  while x < 5\n    x += 1\n  end\nwhile y < 5")
+        );
+        assert_eq!(
+            1,
+            open("forever\n  do\n    x += 1\n  while x < 5\n  while y < 5\n    y += 1\n  end")
+        );
+    }
 }
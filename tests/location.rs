@@ -0,0 +1,49 @@
+mod location_tests {
+    use redstone_compiler::frontend::{Location, Range};
+
+    #[test]
+    fn contains() {
+        let range = Range(Location(1, 4), Location(2, 2));
+        assert!(range.contains(Location(1, 4)));
+        assert!(range.contains(Location(1, 20)));
+        assert!(range.contains(Location(2, 2)));
+        assert!(!range.contains(Location(1, 3)));
+        assert!(!range.contains(Location(2, 3)));
+    }
+
+    #[test]
+    fn overlaps() {
+        let range = Range(Location(0, 3), Location(0, 6));
+        assert!(range.overlaps(Range(Location(0, 6), Location(0, 8))));
+        assert!(range.overlaps(Range(Location(0, 4), Location(0, 5))));
+        assert!(!range.overlaps(Range(Location(0, 7), Location(0, 8))));
+        assert!(!range.overlaps(Range(Location(0, 1), Location(0, 2))));
+    }
+
+    #[test]
+    fn ordering() {
+        let mut ranges = vec![
+            Range(Location(2, 1), Location(2, 3)),
+            Range(Location(0, 5), Location(0, 6)),
+            Range(Location(0, 2), Location(1, 0)),
+            Range(Location(0, 2), Location(0, 4)),
+        ];
+        ranges.sort();
+        assert_eq!(
+            ranges,
+            [
+                Range(Location(0, 2), Location(0, 4)),
+                Range(Location(0, 2), Location(1, 0)),
+                Range(Location(0, 5), Location(0, 6)),
+                Range(Location(2, 1), Location(2, 3)),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "adding a reversed range")]
+    fn add_reversed() {
+        let _ = Range(Location(0, 5), Location(0, 1)) + Range::default();
+    }
+}
@@ -0,0 +1,190 @@
+mod parser_tests {
+    use redstone_compiler::{
+        frontend::{tokenize, Expression, ExpressionType, Parser},
+        Error,
+    };
+
+    fn parse(code: &str) -> Result<Vec<Expression>, Vec<Error>> {
+        let tokens = tokenize(code).expect("Code to tokenize");
+        Parser::new().produce_ast(tokens)
+    }
+
+    fn messages(code: &str) -> Vec<String> {
+        parse(code)
+            .expect_err("Code to fail parsing")
+            .into_iter()
+            .map(|err| err.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn missing_end() {
+        assert_eq!(
+            vec!["Missing end keyword at 1:1-2"],
+            messages("if x == 1\n  pass\n")
+        );
+        assert_eq!(
+            vec!["Missing end keyword at 1:1-7"],
+            messages("forever\n  pass\n")
+        );
+    }
+
+    #[test]
+    fn missing_while() {
+        assert_eq!(
+            vec!["Expected 'while' after the do block at 1:1-2"],
+            messages("do\n  pass\n")
+        );
+    }
+
+    #[test]
+    fn while_in_do() {
+        let code = "do\n  while x < 5\n    x += 1\n  end\nwhile y < 5";
+        let ast = parse(code).expect("Code to parse");
+        let ExpressionType::DoWhile { body, .. } = &ast[0].typ else {
+            panic!("Expected a do-while, got {:?}", ast[0]);
+        };
+        assert!(matches!(body[0].typ, ExpressionType::WhileLoop { .. }));
+
+        // without an end of its own the while closes the do
+        let code =
+            "forever\n  do\n    x += 1\n  while x < 5\n  while y < 5\n    y += 1\n  end\nend";
+        let ast = parse(code).expect("Code to parse");
+        let ExpressionType::EndlessLoop { body } = &ast[0].typ else {
+            panic!("Expected a forever loop, got {:?}", ast[0]);
+        };
+        assert!(matches!(body[0].typ, ExpressionType::DoWhile { .. }));
+        assert!(matches!(body[1].typ, ExpressionType::WhileLoop { .. }));
+
+        // an error in the nested loop is reported, not swallowed
+        let errors = messages("do\n  while x < 5\n    x = (1\n  end\nwhile y < 5");
+        assert_eq!("Unexpected token, expected ')' at 4:3-5", errors[0]);
+    }
+
+    #[test]
+    fn match_arms() {
+        assert_eq!(
+            vec!["Expected ':' after the case value at 2:6"],
+            messages("match x\ncase 1\n  pass\nend")
+        );
+        assert_eq!(
+            vec![
+                "Expected 'case' after the matched value at 2:3-6",
+                "Unexpected token found at 3:1-3"
+            ],
+            messages("match x\n  pass\nend")
+        );
+    }
+
+    #[test]
+    fn array_index() {
+        assert_eq!(
+            vec!["Only arrays can be indexed at 1:5"],
+            messages("x = 5[1]")
+        );
+        assert_eq!(vec!["Missing ']' at 1:8"], messages("var a[4)"));
+    }
+
+    #[test]
+    fn chained_comparison() {
+        assert_eq!(
+            vec!["Comparisons can't be chained, use 'and' to combine them at 1:11"],
+            messages("x = 1 < 2 < 3")
+        );
+        assert!(parse("x = 1 < 2 and 2 < 3").is_ok());
+        assert!(parse("x = (1 < 2) == 1").is_ok());
+    }
+
+    #[test]
+    fn use_alias() {
+        assert!(parse("use screen as s").is_ok());
+        assert_eq!(
+            vec!["Only a single module can get an alias at 1:16"],
+            messages("use io.math as m")
+        );
+    }
+
+    #[test]
+    fn nested_member() {
+        assert_eq!(
+            vec!["Modules only have methods, they can't be nested at 1:9"],
+            messages("x = io.a.b()")
+        );
+        assert_eq!(
+            vec!["Only modules have methods at 1:5"],
+            messages("x = 5.b()")
+        );
+        assert_eq!(
+            vec!["Only functions and methods can be called at 1:5"],
+            messages("x = 5(1)")
+        );
+    }
+
+    #[test]
+    fn increment_in_expression() {
+        let message = "'++' and '--' can't be part of a larger expression, use them on their own";
+        assert_eq!(vec![format!("{message} at 1:5-7")], messages("z = x++ + 5"));
+        assert_eq!(
+            vec![format!("{message} at 1:5-11")],
+            messages("z = 5 + x--")
+        );
+    }
+
+    #[test]
+    fn elif_after_else() {
+        let code = "if c1 == 1\n  pass\nelse\n  pass\nelif c2 == 1\n  pass\nend";
+        assert_eq!(vec!["'elif' cannot follow 'else' at 5:1-4"], messages(code));
+    }
+
+    #[test]
+    fn else_if() {
+        let branches = |code: &str| match &parse(code).expect("Code to parse")[0].typ {
+            ExpressionType::Conditional {
+                paths, alternate, ..
+            } => (paths.len(), alternate.is_some()),
+            _ => panic!("Expected a conditional"),
+        };
+        for spelling in ["elif", "elseif", "else if"] {
+            let code = format!("if a == 1\n  pass\n{spelling} a == 2\n  pass\nelse\n  pass\nend");
+            assert_eq!((1, true), branches(&code), "{spelling}");
+        }
+        // an if on the next line is nested inside the else
+        let code = "if a == 1\n  pass\nelse\n  if a == 2\n    pass\n  end\nend";
+        assert_eq!((0, true), branches(code));
+
+        let code = "if c1 == 1\n  pass\nelse\n  pass\nelse if c2 == 1\n  pass\nend";
+        assert_eq!(vec!["'elif' cannot follow 'else' at 5:1-7"], messages(code));
+    }
+
+    #[test]
+    fn missing_condition() {
+        assert_eq!(
+            vec!["Expected a condition on the same line at 1:1-2"],
+            messages("if end")
+        );
+        assert_eq!(
+            vec!["Expected a condition on the same line at 3:1-7"],
+            messages("if a == 1\n  pass\nelse if\n  pass\nend")
+        );
+        assert_eq!(
+            vec!["Expected a condition on the same line at 1:1-5"],
+            messages("while\n  x = 1\nend")
+        );
+        assert_eq!(
+            vec!["Expected a condition on the same line at 3:1-5"],
+            messages("do\n  pass\nwhile")
+        );
+    }
+
+    #[test]
+    fn function_params() {
+        assert_eq!(
+            vec!["Expected identifier at 1:9"],
+            messages("fn f(a, 1)\n  pass\nend")
+        );
+        assert_eq!(
+            vec!["A function can't have two params with the same name at 1:9"],
+            messages("fn f(a, a)\n  pass\nend")
+        );
+    }
+}